@@ -69,20 +69,26 @@ impl<'a> bgm::MesherViewAdjacent for View<'a> {
 }
 
 fn init() -> (HashMap<IVec3, Chunk>, Blocks) {
-    let context = Blocks(vec![false]);
+    let context = Blocks(vec![false, true]);
 
     let mut map = HashMap::new();
-    let chunk = map
-        .entry(IVec3::ZERO)
-        .or_insert(Chunk([u16::MAX; bgm::CUBE]));
+    map.insert(IVec3::ZERO, Chunk(*bgm::demo_sphere()));
+
+    (map, context)
+}
+
+/// Alternating air (0) and solid (1) voxels, where no faces can merge
+fn init_checkerboard() -> (HashMap<IVec3, Chunk>, Blocks) {
+    let context = Blocks(vec![false, true]);
+
+    let mut map = HashMap::new();
+    let chunk = map.entry(IVec3::ZERO).or_insert(Chunk([0; bgm::CUBE]));
 
     for x in 0..bgm::LEN {
         for y in 0..bgm::LEN {
             for z in 0..bgm::LEN {
                 let i_3d = bgm::Shape::linearize([x, y, z]);
-                if inside_sphere([x as u32 - 31, y as u32 - 31, z as u32 - 31], 16) {
-                    chunk.0[i_3d] = 0;
-                }
+                chunk.0[i_3d] = ((x + y + z) % 2) as u16;
             }
         }
     }
@@ -90,11 +96,6 @@ fn init() -> (HashMap<IVec3, Chunk>, Blocks) {
     (map, context)
 }
 
-fn inside_sphere(pos: [u32; 3], radius: u32) -> bool {
-    let length_squared = pos.into_iter().fold(0, |fold, elem| fold + elem * elem);
-    length_squared < radius.pow(2)
-}
-
 fn mesh(c: &mut Criterion) {
     let (map, context) = black_box(init());
     let view = View::new(&map, IVec3::ZERO).unwrap();
@@ -108,5 +109,35 @@ fn mesh(c: &mut Criterion) {
     });
 }
 
-criterion_group!(mesh_group, mesh);
+fn mesh_checkerboard(c: &mut Criterion) {
+    let (map, context) = black_box(init_checkerboard());
+    let view = View::new(&map, IVec3::ZERO).unwrap();
+
+    let mut mesher = bgm::Mesher::new();
+
+    c.bench_function("mesh_checkerboard", |b| {
+        b.iter(|| {
+            mesher.mesh(&view, &context);
+        });
+    });
+}
+
+#[cfg(feature = "rayon")]
+fn mesh_par(c: &mut Criterion) {
+    let (map, context) = black_box(init());
+    let view = View::new(&map, IVec3::ZERO).unwrap();
+
+    let mut mesher = bgm::Mesher::new();
+
+    c.bench_function("mesh_par", |b| {
+        b.iter(|| {
+            mesher.mesh_par(&view, &context);
+        });
+    });
+}
+
+#[cfg(feature = "rayon")]
+criterion_group!(mesh_group, mesh, mesh_checkerboard, mesh_par);
+#[cfg(not(feature = "rayon"))]
+criterion_group!(mesh_group, mesh, mesh_checkerboard);
 criterion_main!(mesh_group);
@@ -0,0 +1,85 @@
+use enum_map::EnumMap;
+
+use crate::{Face, LEN};
+
+/// Options that change the quads produced by [`Mesher`](crate::Mesher)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MesherConfig {
+    /// Quads are split along a grid of `tile_size` voxels, aligned to the chunk origin,
+    /// so that no quad spans more than one tile. Useful when textures can only repeat a limited number of times.
+    ///
+    /// Must be positive, defaults to [`LEN`] which never splits,
+    /// though quads spanning the whole chunk are always split since their size must fit 6 bits.
+    pub tile_size: usize,
+    /// When `false` faces are never merged and every visible voxel face becomes its own 1x1 quad,
+    /// useful to tell whether a rendering bug comes from merging.
+    ///
+    /// Defaults to `true`.
+    pub greedy: bool,
+    /// Whether faces merge along the x, y and z axes, e.g. `[true, false, false]` for horizontal strips.
+    ///
+    /// Defaults to `[true; 3]`.
+    pub merge_axes: [bool; 3],
+    /// Whether each face merges at all, e.g. `false` on [`Face::PosY`] for 1x1 floor quads that decals don't stretch over.
+    ///
+    /// Defaults to `true` for every face.
+    pub merge_faces: EnumMap<Face, bool>,
+    /// Faces are never merged across a grid of `merge_grid` voxels, aligned to the chunk origin,
+    /// so that every quad lies within a single grid cell, e.g. a virtual texture page.
+    ///
+    /// Must be a power of two, defaults to [`LEN`] which never prevents merging.
+    pub merge_grid: usize,
+    /// Quads with a smaller [`Quad::area`](crate::Quad::area) are dropped, e.g. to simplify distant chunks.
+    /// This leaves holes where they were.
+    ///
+    /// Defaults to `0` which keeps every quad.
+    pub min_quad_area: u32,
+    /// Whether the chunk wraps around along the x, y and z axes, for periodic worlds.
+    /// Faces on a wrapping boundary are culled against the opposite side of the chunk instead of the adjacent chunk.
+    ///
+    /// Defaults to `[false; 3]`.
+    pub wrap: [bool; 3],
+    /// Hides faces that touch [`MesherContext::is_open`](crate::MesherContext::is_open) voxels
+    /// which aren't connected to the chunk's boundary, like sealed air pockets inside terrain.
+    ///
+    /// Adjacent chunks aren't considered, so a void that is only sealed by a neighboring chunk still meshes.
+    /// [`MesherContext::is_open`](crate::MesherContext::is_open) defaults to `true` for every voxel,
+    /// so it must be overridden for this to find any void.
+    /// Defaults to `false`.
+    pub cull_enclosed: bool,
+}
+
+impl Default for MesherConfig {
+    fn default() -> Self {
+        Self {
+            tile_size: LEN,
+            greedy: true,
+            merge_axes: [true; 3],
+            merge_faces: EnumMap::from_array([true; 6]),
+            merge_grid: LEN,
+            min_quad_area: 0,
+            wrap: [false; 3],
+            cull_enclosed: false,
+        }
+    }
+}
+
+impl MesherConfig {
+    /// The grid that `face` can't merge across along each axis, where a grid of `1` stops every merge
+    ///
+    /// # Panics
+    /// If [`MesherConfig::merge_grid`] isn't a power of two
+    pub(crate) fn merge_grids(&self, face: Face) -> [usize; 3] {
+        assert!(
+            self.merge_grid.is_power_of_two(),
+            "merge_grid must be a power of two"
+        );
+        self.merge_axes.map(|merge| {
+            if self.greedy && self.merge_faces[face] && merge {
+                self.merge_grid
+            } else {
+                1
+            }
+        })
+    }
+}
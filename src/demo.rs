@@ -0,0 +1,22 @@
+use crate::{CUBE, LEN, Shape};
+use ndshape::ConstShape as _;
+
+/// The sphere used by the benches, for sharing one fixture between examples, benches and downstream tests.
+///
+/// Voxels strictly closer than 16 to the chunk's center `(31, 31, 31)` are `1`, the rest are air (`0`).
+pub fn demo_sphere() -> Box<[u16; CUBE]> {
+    let mut voxels = Box::new([0; CUBE]);
+
+    for x in 0..LEN {
+        for y in 0..LEN {
+            for z in 0..LEN {
+                let [dx, dy, dz] = [x, y, z].map(|c| c as i32 - 31);
+                if dx * dx + dy * dy + dz * dz < 16 * 16 {
+                    voxels[Shape::linearize([x, y, z])] = 1;
+                }
+            }
+        }
+    }
+
+    voxels
+}
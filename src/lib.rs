@@ -1,9 +1,23 @@
+mod config;
+#[cfg(any(test, feature = "demo"))]
+mod demo;
+mod symmetry;
 mod types;
+mod views;
 
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Range;
+
+pub use config::*;
+#[cfg(any(test, feature = "demo"))]
+pub use demo::*;
 use enum_map::EnumMap;
 use glam::USizeVec3;
 use ndshape::{ConstPow2Shape3usize, ConstShape as _, ConstShape2usize};
+pub use symmetry::*;
 pub use types::*;
+pub use views::*;
 
 pub const BITS: usize = 6;
 pub const LEN: usize = 1 << BITS;
@@ -11,6 +25,14 @@ pub const SQUARE: usize = LEN * LEN;
 pub const CUBE: usize = LEN * LEN * LEN;
 pub type Shape = ConstPow2Shape3usize<BITS, BITS, BITS>;
 
+/// Upper bound on the number of quads a single mesh can produce, useful for sizing fixed buffers.
+///
+/// Every quad covers at least one face of one voxel, and a chunk has `6 * CUBE` voxel faces.
+/// The bound is reached when no two faces can merge, e.g. every voxel visible from every side with a different id from its neighbors.
+pub const fn max_quads() -> usize {
+    Face::ALL.len() * CUBE
+}
+
 const STRIDE_X: usize = 1 << Shape::SHIFTS[0];
 const STRIDE_Y: usize = 1 << Shape::SHIFTS[1];
 
@@ -19,9 +41,18 @@ type PaddedShape = ConstShape2usize<PADDED_LEN, PADDED_LEN>;
 const PADDED_STRIDE_Y_2D: usize = PaddedShape::STRIDES[0];
 const PADDED_STRIDE_Z_2D: usize = PaddedShape::STRIDES[1];
 
+/// Reusable scratch space and output for meshing chunks.
+///
+/// # Concurrency
+/// Meshing takes `&mut self`, so a single [`Mesher`] can't mesh from multiple threads at once.
+/// [`Mesher`] is `Send`, so the recommended pattern is one [`Mesher`] per thread or task,
+/// created from an existing one with [`Mesher::fork`].
 #[derive(Debug, Clone)]
 pub struct Mesher {
     pub quads: Vec<Quad>,
+    pub config: MesherConfig,
+    /// End of each face's quads in `quads` for the last mesh
+    face_ends: EnumMap<Face, usize>,
     /// # Length
     /// Padded along +y and +z (except for the corner) with 0 to avoid branching
     ///
@@ -36,17 +67,27 @@ pub struct Mesher {
     /// | 63 | x  | x  | x  | 0  |
     /// | 64 | 0  | 0  | 0  | _  |
     visible_masks: Box<EnumMap<Face, [u64; PaddedShape::SIZE]>>,
-    forward_merged: Box<[u8; SQUARE]>,
-    upward_merged: Box<[u8; LEN]>,
+    /// One per axis so that axes can be merged in parallel
+    scratch: [MergeScratch; 3],
+    #[cfg(feature = "rayon")]
+    axis_quads: [Vec<Quad>; 3],
+    /// Open voxels connected to the chunk's boundary, one `u64` of x bits per (y, z)
+    reachable: Box<[u64; SQUARE]>,
+    flood_stack: Vec<USizeVec3>,
 }
 
 impl Default for Mesher {
     fn default() -> Self {
         Self {
             quads: Vec::new(),
+            config: MesherConfig::default(),
+            face_ends: EnumMap::default(),
             visible_masks: Box::new(EnumMap::from_array([[0; PaddedShape::SIZE]; 6])),
-            forward_merged: Box::new([0; SQUARE]),
-            upward_merged: Box::new([0; LEN]),
+            scratch: Default::default(),
+            #[cfg(feature = "rayon")]
+            axis_quads: Default::default(),
+            reachable: Box::new([0; SQUARE]),
+            flood_stack: Vec::new(),
         }
     }
 }
@@ -56,288 +97,2196 @@ impl Mesher {
         Self::default()
     }
 
-    pub fn mesh<X, V, C>(&mut self, view: &V, context: &C) -> EnumMap<Face, usize>
-    where
-        X: Copy,
-        V: MesherView<Voxel = X> + MesherViewAdjacent,
-        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
-    {
-        self.build_visible(view, context);
-        let lens = self.face_merging(view, context);
-        self.clear_visible();
-        lens
+    pub fn with_config(config: MesherConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
     }
 
-    fn clear_visible(&mut self) {
-        for mask in self.visible_masks.values_mut() {
-            mask.fill(0);
+    /// Preallocates room for `quads` quads in the output
+    ///
+    /// ```
+    /// use binary_greedy_meshing::{Mesher, Quad};
+    ///
+    /// let mut mesher = Mesher::with_capacity(1024);
+    /// assert!(mesher.is_empty());
+    /// assert!(mesher.quads.capacity() >= 1024);
+    ///
+    /// mesher.quads.push(Quad::new([0; 3], [1, 1], [0; 4], 0));
+    /// assert_eq!(mesher.len(), 1);
+    /// ```
+    pub fn with_capacity(quads: usize) -> Self {
+        Self {
+            quads: Vec::with_capacity(quads),
+            ..Self::default()
         }
     }
 
-    fn build_visible<X, V, C>(&mut self, view: &V, context: &C)
-    where
-        V: MesherView<Voxel = X> + MesherViewAdjacent,
-        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
-    {
-        for z in 0..LEN {
-            for y in 0..LEN {
-                let i_2d = PaddedShape::linearize([y, z]);
+    /// Creates an independent [`Mesher`] with its own scratch buffers and the same [`MesherConfig`].
+    ///
+    /// Unlike [`Clone`] no scratch or output is copied, so this is as cheap as [`Mesher::new`].
+    /// The fork shares no mutable state with `self` and can be sent to another thread.
+    pub fn fork(&self) -> Self {
+        Self::with_config(self.config)
+    }
 
-                for x in 0..LEN {
-                    let pos = USizeVec3::new(x, y, z);
+    /// Releases spare capacity left by meshing large chunks, keeping the quads of the last mesh.
+    ///
+    /// The fixed size masks and scratch are kept, only growable buffers shrink.
+    pub fn shrink_to_fit(&mut self) {
+        self.quads.shrink_to_fit();
+        self.flood_stack.shrink_to_fit();
+        #[cfg(feature = "rayon")]
+        for quads in &mut self.axis_quads {
+            quads.shrink_to_fit();
+        }
+    }
 
-                    let Some(voxel) = context.into_inner(view.get(pos.into())) else {
-                        continue;
-                    };
+    /// The number of quads produced by the last mesh
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
 
-                    let bit = 1 << x;
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
 
-                    for face in Face::ALL {
-                        let new_pos = pos
-                            .as_uvec3()
-                            .wrapping_add_signed(face.to_ivec3())
-                            .as_usizevec3();
-                        let adj_pos = new_pos % LEN;
-                        let external = new_pos != adj_pos;
+    /// The number of triangles needed to draw the last mesh, 2 per quad
+    ///
+    /// ```
+    /// use binary_greedy_meshing::{Mesher, Quad};
+    ///
+    /// let mut mesher = Mesher::new();
+    /// assert!(mesher.is_empty());
+    ///
+    /// mesher.quads.push(Quad::new([0; 3], [1, 1], [0; 4], 0));
+    /// assert_eq!(mesher.triangle_count(), 2 * mesher.len());
+    /// ```
+    pub fn triangle_count(&self) -> usize {
+        2 * self.len()
+    }
 
-                        let adj_voxel = if external {
-                            match view.get_adjacent(adj_pos.into(), face) {
-                                Some(v) => context.into_inner(v),
-                                None => continue,
-                            }
-                        } else {
-                            context.into_inner(view.get(adj_pos.into()))
-                        };
+    /// The total area of the last mesh in voxel faces, e.g. for gameplay that depends on exposed surface
+    pub fn surface_area(&self) -> f32 {
+        self.quads.iter().map(|quad| quad.area() as f32).sum()
+    }
 
-                        if adj_voxel.map_or(false, |adj_voxel| context.is_visible(voxel, adj_voxel))
-                        {
-                            self.visible_masks[face][i_2d] |= bit;
-                        }
-                    }
-                }
-            }
-        }
+    /// GPU memory needed to draw the last mesh as a regular mesh, with 4 vertices and 6 indices per quad
+    ///
+    /// ```
+    /// use binary_greedy_meshing::{Face, FnView, Mesher, MesherContext};
+    ///
+    /// struct Blocks;
+    ///
+    /// impl MesherContext for Blocks {
+    ///     type Voxel = u8;
+    ///     type InnerVoxel = u8;
+    ///
+    ///     fn into_inner(&self, voxel: u8) -> Option<u8> {
+    ///         Some(voxel)
+    ///     }
+    ///
+    ///     fn is_visible(&self, voxel: u8, adj_voxel: u8) -> bool {
+    ///         voxel != 0 && adj_voxel == 0
+    ///     }
+    ///
+    ///     fn can_merge(&self, voxel: u8, adj_voxel: u8) -> bool {
+    ///         voxel == adj_voxel
+    ///     }
+    ///
+    ///     fn u26_shader_id(&self, voxel: u8, _face: Face) -> u32 {
+    ///         voxel as u32
+    ///     }
+    /// }
+    ///
+    /// // a single voxel surrounded by air
+    /// let view = FnView {
+    ///     get: |offset: [usize; 3]| (offset == [1, 1, 1]) as u8,
+    ///     get_adjacent: |_: [usize; 3], _: Face| Some(0),
+    /// };
+    ///
+    /// let mut mesher = Mesher::new();
+    /// mesher.mesh(&view, &Blocks);
+    /// assert_eq!(mesher.len(), 6);
+    /// assert_eq!(mesher.gpu_bytes(12, 4), 6 * (4 * 12 + 6 * 4));
+    /// ```
+    pub fn gpu_bytes(&self, vertex_size: usize, index_size: usize) -> usize {
+        self.len() * (4 * vertex_size + 6 * index_size)
     }
 
-    fn face_merging<X, V, C>(&mut self, view: &V, context: &C) -> EnumMap<Face, usize>
-    where
-        X: Copy,
-        V: MesherView<Voxel = X>,
-        C: MesherContext<Voxel = X>,
-    {
-        self.quads.clear();
+    /// The range of `quads` holding `face`'s quads from the last mesh
+    pub fn face_range(&self, face: Face) -> Range<usize> {
+        let start = match face as usize {
+            0 => 0,
+            i => self.face_ends[Face::ALL[i - 1]],
+        };
+        start..self.face_ends[face]
+    }
 
-        let mut lens = EnumMap::default();
+    pub fn face_quads(&self, face: Face) -> &[Quad] {
+        &self.quads[self.face_range(face)]
+    }
 
-        for face in Face::ALL {
-            match face {
-                Face::PosX | Face::NegX => self.merge_x(view, context, face),
-                Face::NegY | Face::PosY => self.merge_y(view, context, face),
-                Face::PosZ | Face::NegZ => self.merge_z(view, context, face),
-            }
-            lens[face] = self.quads.len()
-        }
+    /// Iterates the quads of the last mesh along with their face, in face order
+    pub fn iter_quads(&self) -> impl Iterator<Item = (Face, Quad)> + '_ {
+        Face::ALL
+            .into_iter()
+            .flat_map(move |face| self.face_quads(face).iter().map(move |&quad| (face, quad)))
+    }
 
-        lens
+    /// Collects [`Mesher::iter_quads`], for building a single buffer with a per quad face attribute
+    pub fn flatten(&self) -> Vec<(Face, Quad)> {
+        let mut out = Vec::with_capacity(self.len());
+        self.flatten_into(&mut out);
+        out
     }
 
-    fn merge_x<X, V, C>(&mut self, view: &V, context: &C, face: Face)
-    where
-        X: Copy,
-        V: MesherView<Voxel = X>,
-        C: MesherContext<Voxel = X>,
-    {
-        for z in 0..LEN {
-            for y in 0..LEN {
-                let i_2d = PaddedShape::linearize([y, z]);
+    /// Same as [`Mesher::flatten`] but appends to `out`, reusing its allocation
+    pub fn flatten_into(&self, out: &mut Vec<(Face, Quad)>) {
+        out.extend(self.iter_quads());
+    }
 
-                let mut visible = self.visible_masks[face][i_2d];
-                let upward_visible = self.visible_masks[face][i_2d + PADDED_STRIDE_Y_2D];
-                let forward_visible = self.visible_masks[face][i_2d + PADDED_STRIDE_Z_2D];
+    /// Same as [`Mesher::iter_quads`] but ordered by the quads' lowest position along `axis`, e.g. for back to front rendering
+    pub fn iter_sorted_by_axis(&self, axis: usize) -> std::vec::IntoIter<(Face, Quad)> {
+        let mut quads: Vec<_> = self.iter_quads().collect();
+        quads.sort_by_key(|&(face, quad)| quad.aabb(face).0[axis]);
+        quads.into_iter()
+    }
 
-                while visible != 0 {
-                    let x = visible.trailing_zeros() as usize;
-                    visible &= visible - 1;
+    /// Finds the quad of the last mesh covering `face` of `voxel`, e.g. to look up its UV span when baking lightmaps.
+    ///
+    /// Scans `face`'s quads in O(n). They are ordered by the row each merge finished on rather than by plane,
+    /// so there is no key to binary search; build a lookup from [`Quad::voxel_faces`] for many queries.
+    pub fn quad_at(&self, face: Face, voxel: [u32; 3]) -> Option<&Quad> {
+        let axis = face.axis();
+        let [u_axis, v_axis] = face.size_axes();
 
-                    let upward_i = Shape::linearize([x, 0, 0]);
-                    let forward_i = Shape::linearize([x, y, 0]);
+        self.face_quads(face).iter().find(|quad| {
+            let xyz = quad.xyz();
+            xyz[axis] == voxel[axis]
+                && (xyz[u_axis]..xyz[u_axis] + quad.w()).contains(&voxel[u_axis])
+                && (xyz[v_axis]..xyz[v_axis] + quad.h()).contains(&voxel[v_axis])
+        })
+    }
 
-                    let pos = USizeVec3::new(x, y, z);
-                    let voxel = view.get(pos.into());
+    /// Finds the quads of the last mesh sharing an edge with each quad in the same face plane, e.g. for mesh post-processing.
+    ///
+    /// Indexed like `quads`, and so are the neighbors.
+    pub fn quad_adjacency(&self) -> Vec<Vec<usize>> {
+        let mut adjacency = vec![Vec::new(); self.len()];
+        // quads only touch within a plane, so only those are compared
+        let mut planes = vec![Vec::new(); LEN + 1];
 
-                    // forward merging
-                    if self.upward_merged[upward_i] == 0
-                        && (forward_visible >> x) & 1 != 0
-                        && context.can_merge(voxel, view.get(pos.with_z(pos.z + 1).into()))
-                    {
-                        self.forward_merged[forward_i] += 1;
-                        continue;
-                    }
+        for face in Face::ALL {
+            let axis = face.axis();
+            let [u_axis, v_axis] = face.size_axes();
 
-                    // upward merging
-                    if (upward_visible >> x) & 1 != 0
-                        && self.forward_merged[forward_i]
-                            == self.forward_merged[forward_i + STRIDE_Y]
-                        && context.can_merge(voxel, view.get(pos.with_y(pos.y + 1).into()))
-                    {
-                        self.forward_merged[forward_i] = 0;
-                        self.upward_merged[upward_i] += 1;
-                        continue;
+            for plane in &mut planes {
+                plane.clear();
+            }
+            for i in self.face_range(face) {
+                planes[self.quads[i].aabb(face).0[axis] as usize].push(i);
+            }
+
+            for plane in &planes {
+                for &i in plane {
+                    let (a_min, a_max) = self.quads[i].aabb(face);
+                    for &j in plane.iter().filter(|&&j| j != i) {
+                        let (b_min, b_max) = self.quads[j].aabb(face);
+
+                        let touches = |t: usize, o: usize| {
+                            (a_max[t] == b_min[t] || b_max[t] == a_min[t])
+                                && a_min[o] < b_max[o]
+                                && b_min[o] < a_max[o]
+                        };
+                        if touches(u_axis, v_axis) || touches(v_axis, u_axis) {
+                            adjacency[i].push(j);
+                        }
                     }
+                }
+            }
+        }
 
-                    // finish
-                    self.quads.push({
-                        let forward_merged = self.forward_merged[forward_i] as u32;
-                        let upward_merged = self.upward_merged[upward_i] as u32;
+        adjacency
+    }
 
-                        let x = x as u32;
-                        let y = y as u32 - upward_merged;
-                        let z = z as u32 - forward_merged;
+    /// The `(min, max)` corners of each quad of the last mesh, in the order of [`Mesher::iter_quads`].
+    ///
+    /// Quads are flat, so boxes are given a `thickness` along their face's axis for consumers that need a volume.
+    pub fn aabbs(&self, thickness: f32) -> impl Iterator<Item = ([f32; 3], [f32; 3])> + '_ {
+        self.iter_quads().map(move |(face, quad)| {
+            let (min, max) = quad.aabb(face);
+            let mut min = min.map(|c| c as f32);
+            let mut max = max.map(|c| c as f32);
 
-                        let w = forward_merged + 1;
-                        let h = upward_merged + 1;
+            min[face.axis()] -= thickness / 2.0;
+            max[face.axis()] += thickness / 2.0;
 
-                        let id = context.u26_shader_id(context.into_inner(voxel).unwrap(), face);
+            (min, max)
+        })
+    }
 
-                        Quad::new([x, y, z], [w, h], [0; 4], id)
-                    });
+    /// The tight `(min, max)` corners around every quad of the last mesh, `None` if it is empty
+    pub fn bounds(&self) -> Option<([u32; 3], [u32; 3])> {
+        self.iter_quads()
+            .map(|(face, quad)| quad.aabb(face))
+            .reduce(|(min, max), (quad_min, quad_max)| {
+                (
+                    std::array::from_fn(|i| min[i].min(quad_min[i])),
+                    std::array::from_fn(|i| max[i].max(quad_max[i])),
+                )
+            })
+    }
 
-                    self.forward_merged[forward_i] = 0;
-                    self.upward_merged[upward_i] = 0;
+    /// Finds the nearest quad of the last mesh hit by a ray, along with the distance in units of `dir`.
+    ///
+    /// `origin` is relative to the chunk's origin, only quads facing the ray are hit.
+    pub fn raycast(&self, origin: [f32; 3], dir: [f32; 3]) -> Option<(Face, Quad, f32)> {
+        self.iter_quads()
+            .filter_map(|(face, quad)| {
+                let axis = face.axis();
+                if face.is_positive() == (dir[axis] >= 0.0) {
+                    return None;
                 }
-            }
-        }
-    }
 
-    fn merge_y<X, V, C>(&mut self, view: &V, context: &C, face: Face)
-    where
-        X: Copy,
-        V: MesherView<Voxel = X>,
-        C: MesherContext<Voxel = X>,
-    {
-        for z in 0..LEN {
-            for y in 0..LEN {
-                let i_2d = PaddedShape::linearize([y, z]);
+                let (min, max) = quad.aabb(face);
+                let t = (min[axis] as f32 - origin[axis]) / dir[axis];
+                if t < 0.0 {
+                    return None;
+                }
 
-                let mut visible = self.visible_masks[face][i_2d];
-                let forward_visible = self.visible_masks[face][i_2d + PADDED_STRIDE_Z_2D];
+                let hit = face.size_axes().into_iter().all(|i| {
+                    let c = origin[i] + dir[i] * t;
+                    (min[i] as f32..=max[i] as f32).contains(&c)
+                });
 
-                while visible != 0 {
-                    let x = visible.trailing_zeros() as usize;
+                hit.then_some((face, quad, t))
+            })
+            .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+    }
 
-                    let forward_i = Shape::linearize([x, y, 0]);
+    /// Stably sorts each face's quads by `key`, e.g. by depth for rendering.
+    ///
+    /// Quads never move between faces, so [`Mesher::face_range`] stays valid.
+    pub fn sort_faces_by<K: Ord>(&mut self, mut key: impl FnMut(Face, &Quad) -> K) {
+        for face in Face::ALL {
+            let range = self.face_range(face);
+            self.quads[range].sort_by_key(|quad| key(face, quad));
+        }
+    }
 
-                    let pos = USizeVec3::new(x, y, z);
-                    let voxel = view.get(pos.into());
+    /// Stably sorts each face's quads by id and returns their `(id, range)` runs, with ranges indexing [`Mesher::face_quads`].
+    ///
+    /// Lets a renderer bind a whole face and issue one draw per material.
+    pub fn group_by_material(&mut self) -> EnumMap<Face, Vec<(u32, Range<usize>)>> {
+        EnumMap::from_fn(|face| {
+            let range = self.face_range(face);
+            let quads = &mut self.quads[range];
+            quads.sort_by_key(|quad| quad.id());
 
-                    // forward merging
-                    if (forward_visible >> x) & 1 != 0
-                        && context.can_merge(voxel, view.get(pos.with_z(pos.z + 1).into()))
-                    {
-                        self.forward_merged[forward_i] += 1;
-                        visible &= visible - 1;
-                        continue;
-                    }
+            let mut start = 0;
+            quads
+                .chunk_by(|a, b| a.id() == b.id())
+                .map(|run| {
+                    let range = start..start + run.len();
+                    start = range.end;
+                    (run[0].id(), range)
+                })
+                .collect()
+        })
+    }
 
-                    // rightward merging
-                    let mut i = 1;
-                    while i < (LEN - x)
-                        && (visible >> x >> i) & 1 != 0
-                        && self.forward_merged[forward_i]
-                            == self.forward_merged[forward_i + i * STRIDE_X]
-                        && context.can_merge(voxel, view.get(pos.with_x(pos.x + 1).into()))
-                    {
-                        self.forward_merged[forward_i + i * STRIDE_X] = 0;
-                        i += 1;
-                    }
-                    let right_merged = i;
-                    visible &= !((1 << x << i) - 1);
+    /// Rotates and/or mirrors the last mesh within the chunk, moving quads between faces as needed.
+    ///
+    /// Covers the same faces as meshing the transformed voxels, though greedy merging there may split them differently.
+    pub fn transform_octahedral(&mut self, symmetry: OctahedralSymmetry) {
+        let transformed: Vec<_> = self
+            .iter_quads()
+            .map(|(face, quad)| symmetry.apply(face, quad))
+            .collect();
 
-                    // finish
-                    self.quads.push({
-                        let forward_merged = self.forward_merged[forward_i] as u32;
+        self.quads.clear();
 
-                        let x = x as u32;
-                        let y = y as u32;
-                        let z = z as u32 - forward_merged;
+        for face in Face::ALL {
+            self.quads.extend(
+                transformed
+                    .iter()
+                    .filter(|&&(quad_face, _)| quad_face == face)
+                    .map(|&(_, quad)| quad),
+            );
+            self.face_ends[face] = self.quads.len();
+        }
+    }
 
-                        let w = right_merged as u32;
-                        let h = forward_merged + 1;
+    /// `face`'s quads as raw bytes, e.g. for writing to a file that is later read back with [`Quad::cast_slice`]
+    pub fn face_bytes(&self, face: Face) -> &[u8] {
+        bytemuck::cast_slice(self.face_quads(face))
+    }
 
-                        let id = context.u26_shader_id(context.into_inner(voxel).unwrap(), face);
+    pub fn mesh<X, V, C>(&mut self, view: &V, context: &C) -> EnumMap<Face, usize>
+    where
+        X: Copy,
+        V: MesherView<Voxel = X> + MesherViewAdjacent,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        self.build(view, context);
+        self.merge(view, context)
+    }
 
-                        Quad::new([x, y, z], [w, h], [0; 4], id)
-                    });
+    /// Same as [`Mesher::mesh`] but passes every merged quad through `f`, which can edit it or return `false` to drop it.
+    ///
+    /// Runs after merging, so dropping a quad never changes how its neighbors merge.
+    pub fn mesh_filtered<X, V, C>(
+        &mut self,
+        view: &V,
+        context: &C,
+        mut f: impl FnMut(Face, &mut Quad) -> bool,
+    ) -> EnumMap<Face, usize>
+    where
+        X: Copy,
+        V: MesherView<Voxel = X> + MesherViewAdjacent,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        self.mesh(view, context);
 
-                    self.forward_merged[forward_i] = 0
+        let mut face_ends = EnumMap::default();
+        let mut kept = 0;
+        for face in Face::ALL {
+            for i in self.face_range(face) {
+                let mut quad = self.quads[i];
+                if f(face, &mut quad) {
+                    self.quads[kept] = quad;
+                    kept += 1;
                 }
             }
+            face_ends[face] = kept;
         }
+        self.quads.truncate(kept);
+        self.face_ends = face_ends;
+
+        face_ends
     }
 
-    fn merge_z<X, V, C>(&mut self, view: &V, context: &C, face: Face)
+    /// The first half of [`Mesher::mesh`], builds which faces are visible.
+    ///
+    /// [`Mesher::merge`] can then run later, e.g. on the next frame.
+    pub fn build<X, V, C>(&mut self, view: &V, context: &C)
+    where
+        V: MesherView<Voxel = X> + MesherViewAdjacent,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        self.clear_visible();
+        self.build_visible::<false, _, _, _>(view, context, [0; 3], [LEN; 3], None);
+    }
+
+    /// The second half of [`Mesher::mesh`], merges the faces left by the last [`Mesher::build`] into quads.
+    ///
+    /// `view` and `context` must hold the same voxels as when building.
+    pub fn merge<X, V, C>(&mut self, view: &V, context: &C) -> EnumMap<Face, usize>
     where
         X: Copy,
         V: MesherView<Voxel = X>,
         C: MesherContext<Voxel = X>,
     {
-        for z in 0..LEN {
-            for y in 0..LEN {
-                let i_2d = PaddedShape::linearize([y, z]);
+        self.face_merging(view, context)
+    }
 
-                let mut visible = self.visible_masks[face][i_2d];
-                let upward_visible = self.visible_masks[face][i_2d + PADDED_STRIDE_Y_2D];
+    /// Same as [`Mesher::mesh`], but the faces of each axis are merged in parallel with [`rayon`].
+    ///
+    /// The output is identical to [`Mesher::mesh`].
+    #[cfg(feature = "rayon")]
+    pub fn mesh_par<X, V, C>(&mut self, view: &V, context: &C) -> EnumMap<Face, usize>
+    where
+        X: Copy,
+        V: MesherView<Voxel = X> + MesherViewAdjacent + Sync,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy> + Sync,
+    {
+        self.build(view, context);
+        self.face_merging_par(view, context)
+    }
 
-                while visible != 0 {
-                    let x = visible.trailing_zeros() as usize;
+    /// Same as [`Mesher::mesh`], but whole face directions that can't face `camera` are skipped.
+    ///
+    /// `camera` is relative to the chunk's origin. A direction is kept when the camera is in front of
+    /// any plane its faces could lie on, so no visible face is dropped,
+    /// but the chunk must be remeshed when the camera crosses its bounds.
+    pub fn mesh_culled<X, V, C>(
+        &mut self,
+        view: &V,
+        context: &C,
+        camera: [f32; 3],
+    ) -> EnumMap<Face, usize>
+    where
+        X: Copy,
+        V: MesherView<Voxel = X> + MesherViewAdjacent,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        self.build(view, context);
 
-                    let upward_i = Shape::linearize([x, 0, 0]);
+        for face in Face::ALL {
+            let camera = camera[face.axis()];
+            // positive faces lie on planes 1..=LEN and negative faces on 0..LEN
+            let facing = if face.is_positive() {
+                camera > 1.0
+            } else {
+                camera < (LEN - 1) as f32
+            };
 
-                    let pos = USizeVec3::new(x, y, z);
-                    let voxel = view.get(pos.into());
+            if !facing {
+                self.visible_masks[face].fill(0);
+            }
+        }
 
-                    // upward merging
-                    if (upward_visible >> x) & 1 != 0
-                        && context.can_merge(voxel, view.get(pos.with_y(pos.y + 1).into()))
-                    {
-                        self.upward_merged[upward_i] += 1;
+        self.face_merging(view, context)
+    }
+
+    /// Meshes only the faces on the chunk's six outer planes, as if it was surrounded by `outside` voxels (usually air),
+    /// e.g. as a cheap conservative occluder for occlusion culling.
+    ///
+    /// Adjacent chunks aren't read, so the occluder of a solid chunk is its full shell whether or not its neighbors are loaded.
+    pub fn mesh_occluder<X, V, C>(
+        &mut self,
+        view: &V,
+        context: &C,
+        outside: C::InnerVoxel,
+    ) -> EnumMap<Face, usize>
+    where
+        X: Copy,
+        V: MesherView<Voxel = X>,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        self.clear_visible();
+
+        for face in Face::ALL {
+            let [u_axis, v_axis] = face.size_axes();
+
+            for v in 0..LEN {
+                for u in 0..LEN {
+                    let mut pos = [0; 3];
+                    pos[face.axis()] = if face.is_positive() { LEN - 1 } else { 0 };
+                    pos[u_axis] = u;
+                    pos[v_axis] = v;
+
+                    let visible = context
+                        .into_inner(view.get(pos))
+                        .is_some_and(|voxel| context.is_face_visible(voxel, outside, face));
+
+                    if visible {
+                        let [x, y, z] = pos;
+                        self.visible_masks[face][PaddedShape::linearize([y, z])] |= 1 << x;
+                    }
+                }
+            }
+        }
+
+        self.face_merging(view, context)
+    }
+
+    /// Meshes only the voxels in `min..max`, as if the rest of the chunk was filled with `outside` voxels, usually air.
+    ///
+    /// Faces on the boundary of the region are tested against `outside`,
+    /// except where it lies on the chunk's boundary, where adjacent chunks are used like in [`Mesher::mesh`].
+    pub fn mesh_subregion<X, V, C>(
+        &mut self,
+        view: &V,
+        context: &C,
+        min: [usize; 3],
+        max: [usize; 3],
+        outside: C::InnerVoxel,
+    ) -> EnumMap<Face, usize>
+    where
+        X: Copy,
+        V: MesherView<Voxel = X> + MesherViewAdjacent,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        debug_assert!(max.iter().all(|&m| m <= LEN), "region exceeds the chunk");
+
+        self.clear_visible();
+        self.build_visible::<true, _, _, _>(view, context, min, max, Some(outside));
+        self.face_merging(view, context)
+    }
+
+    /// Calls `f` with the position and face of every visible voxel face, without merging.
+    ///
+    /// Rebuilds the visibility, e.g. to spawn particles on individual exposed voxels.
+    pub fn exposed_faces<X, V, C>(
+        &mut self,
+        view: &V,
+        context: &C,
+        mut f: impl FnMut([usize; 3], Face),
+    ) where
+        V: MesherView<Voxel = X> + MesherViewAdjacent,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        self.build(view, context);
+
+        for face in Face::ALL {
+            for z in 0..LEN {
+                for y in 0..LEN {
+                    let mut visible = self.visible_masks[face][PaddedShape::linearize([y, z])];
+
+                    while visible != 0 {
+                        let x = visible.trailing_zeros() as usize;
                         visible &= visible - 1;
-                        continue;
+
+                        f([x, y, z], face);
                     }
+                }
+            }
+        }
+    }
 
-                    // rightward merging
-                    let mut i = 1;
-                    while i < (LEN - x)
-                        && (visible >> x >> i) & 1 != 0
-                        && self.upward_merged[upward_i]
-                            == self.upward_merged[upward_i + i * STRIDE_X]
-                        && context.can_merge(voxel, view.get(pos.with_x(pos.x + 1).into()))
-                    {
-                        self.upward_merged[upward_i + i * STRIDE_X] = 0;
-                        i += 1;
+    /// Counts `(surface, buried)` voxels per inner voxel, where surface voxels have at least one visible face.
+    ///
+    /// Rebuilds the visibility without merging, voxels that are never visible like air count as buried.
+    pub fn surface_voxel_counts<X, V, C>(
+        &mut self,
+        view: &V,
+        context: &C,
+    ) -> HashMap<C::InnerVoxel, (usize, usize)>
+    where
+        V: MesherView<Voxel = X> + MesherViewAdjacent,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy + Eq + Hash>,
+    {
+        self.build(view, context);
+
+        let mut counts = HashMap::new();
+
+        for z in 0..LEN {
+            for y in 0..LEN {
+                let i_2d = PaddedShape::linearize([y, z]);
+                let surface = self
+                    .visible_masks
+                    .values()
+                    .fold(0, |acc, mask| acc | mask[i_2d]);
+
+                for x in 0..LEN {
+                    let Some(voxel) = context.into_inner(view.get([x, y, z])) else {
+                        continue;
+                    };
+
+                    let (surface_count, buried_count) = counts.entry(voxel).or_insert((0, 0));
+                    if (surface >> x) & 1 != 0 {
+                        *surface_count += 1;
+                    } else {
+                        *buried_count += 1;
                     }
-                    let right_merged = i;
-                    visible &= !((1 << x << i) - 1);
+                }
+            }
+        }
 
-                    // finish
-                    self.quads.push({
-                        let upward_merged = self.upward_merged[upward_i] as u32;
+        counts
+    }
 
-                        let x = x as u32;
-                        let y = y as u32 - upward_merged;
-                        let z = z as u32;
+    /// Renders the visible faces of the last mesh on the `z` layer as a grid of `#` (visible) and `.` (hidden).
+    ///
+    /// Each line is a row of `x` with `y = 0` first.
+    pub fn debug_visible_layer(&self, face: Face, z: usize) -> String {
+        let mut out = String::with_capacity((LEN + 1) * LEN);
 
-                        let w = right_merged as u32;
-                        let h = upward_merged + 1;
+        for y in 0..LEN {
+            let visible = self.visible_masks[face][PaddedShape::linearize([y, z])];
 
-                        let id = context.u26_shader_id(context.into_inner(voxel).unwrap(), face);
+            for x in 0..LEN {
+                out.push(if (visible >> x) & 1 != 0 { '#' } else { '.' });
+            }
+            out.push('\n');
+        }
 
-                        Quad::new([x, y, z], [w, h], [0; 4], id)
-                    });
+        out
+    }
 
-                    self.upward_merged[upward_i] = 0;
+    /// Copies out the visible faces of the last mesh or [`Mesher::build`], e.g. to feed other merging algorithms the same data.
+    ///
+    /// Each face holds one `u64` of x bits per `(y, z)`, at index `y + z * LEN`.
+    pub fn take_visible(&self) -> EnumMap<Face, Box<[u64; SQUARE]>> {
+        EnumMap::from_fn(|face| {
+            let mut out = Box::new([0; SQUARE]);
+            for z in 0..LEN {
+                for y in 0..LEN {
+                    out[y + z * LEN] = self.visible_masks[face][PaddedShape::linearize([y, z])];
+                }
+            }
+            out
+        })
+    }
+
+    fn clear_visible(&mut self) {
+        for mask in self.visible_masks.values_mut() {
+            mask.fill(0);
+        }
+    }
+
+    /// Builds the visible masks of the voxels in `min..max`,
+    /// faces touching voxels of the chunk outside of it are tested against `outside` instead.
+    ///
+    /// Without `REGION` the whole chunk is built and the region check compiles out of the inner loop.
+    fn build_visible<const REGION: bool, X, V, C>(
+        &mut self,
+        view: &V,
+        context: &C,
+        min: [usize; 3],
+        max: [usize; 3],
+        outside: Option<C::InnerVoxel>,
+    ) where
+        V: MesherView<Voxel = X> + MesherViewAdjacent,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        if self.config.cull_enclosed {
+            self.flood_reachable(view, context);
+        }
+
+        // branching once here so the default config compiles to a loop without wrapping or reachability checks
+        match (self.config.cull_enclosed, self.config.wrap.contains(&true)) {
+            (false, false) => self.build_visible_rows::<REGION, false, false, _, _, _>(
+                view, context, min, max, outside,
+            ),
+            (false, true) => self.build_visible_rows::<REGION, false, true, _, _, _>(
+                view, context, min, max, outside,
+            ),
+            (true, false) => self.build_visible_rows::<REGION, true, false, _, _, _>(
+                view, context, min, max, outside,
+            ),
+            (true, true) => self.build_visible_rows::<REGION, true, true, _, _, _>(
+                view, context, min, max, outside,
+            ),
+        }
+    }
+
+    /// Kept out of line so each instantiation compiles to its own tight loop instead of bloating [`Mesher::build`]
+    #[inline(never)]
+    fn build_visible_rows<
+        const REGION: bool,
+        const CULL_ENCLOSED: bool,
+        const WRAP: bool,
+        X,
+        V,
+        C,
+    >(
+        &mut self,
+        view: &V,
+        context: &C,
+        min: [usize; 3],
+        max: [usize; 3],
+        outside: Option<C::InnerVoxel>,
+    ) where
+        V: MesherView<Voxel = X> + MesherViewAdjacent,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        let (min, max) = if REGION {
+            (min, max)
+        } else {
+            ([0; 3], [LEN; 3])
+        };
+        let wrap = self.config.wrap;
+        let in_region =
+            |pos: USizeVec3| !REGION || (0..3).all(|i| (min[i]..max[i]).contains(&pos[i]));
+
+        for z in min[2]..max[2] {
+            for y in min[1]..max[1] {
+                let i_2d = PaddedShape::linearize([y, z]);
+                // collected in registers and written once per row
+                let mut row_masks = EnumMap::<Face, u64>::default();
+
+                for x in min[0]..max[0] {
+                    let pos = USizeVec3::new(x, y, z);
+
+                    let Some(voxel) = context.into_inner(view.get(pos.into())) else {
+                        continue;
+                    };
+
+                    let bit = 1 << x;
+
+                    for face in Face::ALL {
+                        let new_pos = pos
+                            .as_uvec3()
+                            .wrapping_add_signed(face.to_ivec3())
+                            .as_usizevec3();
+                        let adj_pos = new_pos % LEN;
+                        let external = new_pos != adj_pos;
+
+                        let adj_voxel = if WRAP && external && wrap[face.axis()] {
+                            // `adj_pos` already wrapped around to the opposite boundary, which may lie outside the region
+                            if in_region(adj_pos) {
+                                context.into_inner(view.get(adj_pos.into()))
+                            } else {
+                                outside
+                            }
+                        } else if external {
+                            match view.get_adjacent(adj_pos.into(), face) {
+                                Some(v) => context.into_inner(v),
+                                None => continue,
+                            }
+                        } else if in_region(adj_pos) {
+                            let adj_voxel = context.into_inner(view.get(adj_pos.into()));
+
+                            if CULL_ENCLOSED
+                                && adj_voxel.is_some_and(|adj_voxel| context.is_open(adj_voxel))
+                                && !self.is_reachable(adj_pos)
+                            {
+                                continue;
+                            }
+
+                            adj_voxel
+                        } else {
+                            outside
+                        };
+
+                        if adj_voxel.is_some_and(|adj_voxel| {
+                            context.is_face_visible(voxel, adj_voxel, face)
+                        }) {
+                            row_masks[face] |= bit;
+                        }
+                    }
+                }
+
+                for (face, row_mask) in row_masks {
+                    self.visible_masks[face][i_2d] |= row_mask;
+                }
+            }
+        }
+    }
+
+    /// Flood fills [`MesherContext::is_open`] voxels from the chunk's boundary into `self.reachable`
+    fn flood_reachable<X, V, C>(&mut self, view: &V, context: &C)
+    where
+        V: MesherView<Voxel = X>,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        self.reachable.fill(0);
+
+        for z in 0..LEN {
+            for y in 0..LEN {
+                for x in 0..LEN {
+                    if [x, y, z].iter().any(|&c| c == 0 || c == LEN - 1) {
+                        self.reach(view, context, USizeVec3::new(x, y, z));
+                    }
+                }
+            }
+        }
+
+        while let Some(pos) = self.flood_stack.pop() {
+            for face in Face::ALL {
+                let new_pos = pos
+                    .as_uvec3()
+                    .wrapping_add_signed(face.to_ivec3())
+                    .as_usizevec3();
+
+                if new_pos.max_element() < LEN {
+                    self.reach(view, context, new_pos);
                 }
             }
         }
     }
+
+    /// Marks `pos` as reachable and queues it for the flood fill, unless it already is or isn't open
+    fn reach<X, V, C>(&mut self, view: &V, context: &C, pos: USizeVec3)
+    where
+        V: MesherView<Voxel = X>,
+        C: MesherContext<Voxel = X, InnerVoxel: Copy>,
+    {
+        if self.is_reachable(pos)
+            || !context
+                .into_inner(view.get(pos.into()))
+                .is_some_and(|voxel| context.is_open(voxel))
+        {
+            return;
+        }
+
+        self.reachable[Shape::linearize([pos.y, pos.z, 0])] |= 1 << pos.x;
+        self.flood_stack.push(pos);
+    }
+
+    fn is_reachable(&self, pos: USizeVec3) -> bool {
+        (self.reachable[Shape::linearize([pos.y, pos.z, 0])] >> pos.x) & 1 != 0
+    }
+
+    fn face_merging<X, V, C>(&mut self, view: &V, context: &C) -> EnumMap<Face, usize>
+    where
+        X: Copy,
+        V: MesherView<Voxel = X>,
+        C: MesherContext<Voxel = X>,
+    {
+        self.quads.clear();
+
+        let mut lens = EnumMap::default();
+
+        for face in Face::ALL {
+            self.scratch[face.axis()]
+                .merger(&self.config, &self.visible_masks, &mut self.quads)
+                .merge_face(view, context, face);
+            lens[face] = self.quads.len()
+        }
+
+        self.face_ends = lens;
+        lens
+    }
+
+    /// Merges the faces of each axis on a separate thread, each with its own scratch and output
+    #[cfg(feature = "rayon")]
+    fn face_merging_par<X, V, C>(&mut self, view: &V, context: &C) -> EnumMap<Face, usize>
+    where
+        X: Copy,
+        V: MesherView<Voxel = X> + Sync,
+        C: MesherContext<Voxel = X> + Sync,
+    {
+        let config = &self.config;
+        let visible_masks = &*self.visible_masks;
+        let [scratch_x, scratch_y, scratch_z] = &mut self.scratch;
+        let [quads_x, quads_y, quads_z] = &mut self.axis_quads;
+
+        let (lens_x, (lens_y, lens_z)) = rayon::join(
+            || {
+                quads_x.clear();
+                scratch_x
+                    .merger(config, visible_masks, quads_x)
+                    .merge_faces(view, context, [Face::PosX, Face::NegX])
+            },
+            || {
+                rayon::join(
+                    || {
+                        quads_y.clear();
+                        scratch_y
+                            .merger(config, visible_masks, quads_y)
+                            .merge_faces(view, context, [Face::PosY, Face::NegY])
+                    },
+                    || {
+                        quads_z.clear();
+                        scratch_z
+                            .merger(config, visible_masks, quads_z)
+                            .merge_faces(view, context, [Face::PosZ, Face::NegZ])
+                    },
+                )
+            },
+        );
+
+        self.quads.clear();
+
+        let mut lens = EnumMap::default();
+
+        for (axis, (faces, axis_lens)) in [
+            ([Face::PosX, Face::NegX], lens_x),
+            ([Face::PosY, Face::NegY], lens_y),
+            ([Face::PosZ, Face::NegZ], lens_z),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let offset = self.quads.len();
+            self.quads.extend_from_slice(&self.axis_quads[axis]);
+
+            for (face, len) in faces.into_iter().zip(axis_lens) {
+                lens[face] = offset + len;
+            }
+        }
+
+        self.face_ends = lens;
+        lens
+    }
+}
+
+/// Scratch space for merging the faces of one axis
+#[derive(Debug, Clone)]
+struct MergeScratch {
+    forward_merged: Box<[u8; SQUARE]>,
+    upward_merged: Box<[u8; LEN]>,
+}
+
+impl Default for MergeScratch {
+    fn default() -> Self {
+        Self {
+            forward_merged: Box::new([0; SQUARE]),
+            upward_merged: Box::new([0; LEN]),
+        }
+    }
+}
+
+impl MergeScratch {
+    fn merger<'a>(
+        &'a mut self,
+        config: &'a MesherConfig,
+        visible_masks: &'a EnumMap<Face, [u64; PaddedShape::SIZE]>,
+        quads: &'a mut Vec<Quad>,
+    ) -> Merger<'a> {
+        Merger {
+            config,
+            // neither can cut a quad whose sizes fit in 6 bits
+            split_quads: config.tile_size < LEN || config.min_quad_area > 0,
+            visible_masks,
+            forward_merged: &mut self.forward_merged,
+            upward_merged: &mut self.upward_merged,
+            quads,
+        }
+    }
+}
+
+/// Merges visible faces into quads, pushing them to `quads`
+struct Merger<'a> {
+    config: &'a MesherConfig,
+    /// Whether [`Merger::push_quad`] may need to split or drop quads for the tile size or minimum area
+    split_quads: bool,
+    visible_masks: &'a EnumMap<Face, [u64; PaddedShape::SIZE]>,
+    forward_merged: &'a mut [u8; SQUARE],
+    upward_merged: &'a mut [u8; LEN],
+    quads: &'a mut Vec<Quad>,
+}
+
+impl Merger<'_> {
+    fn merge_face<X, V, C>(&mut self, view: &V, context: &C, face: Face)
+    where
+        X: Copy,
+        V: MesherView<Voxel = X>,
+        C: MesherContext<Voxel = X>,
+    {
+        assert!(self.config.tile_size > 0, "tile_size must be positive");
+
+        match face {
+            Face::PosX | Face::NegX => self.merge_x(view, context, face),
+            Face::NegY | Face::PosY => self.merge_y(view, context, face),
+            Face::PosZ | Face::NegZ => self.merge_z(view, context, face),
+        }
+    }
+
+    /// Merges each face in order, returning the length of `quads` after each
+    #[cfg(feature = "rayon")]
+    fn merge_faces<X, V, C, const N: usize>(
+        mut self,
+        view: &V,
+        context: &C,
+        faces: [Face; N],
+    ) -> [usize; N]
+    where
+        X: Copy,
+        V: MesherView<Voxel = X>,
+        C: MesherContext<Voxel = X>,
+    {
+        faces.map(|face| {
+            self.merge_face(view, context, face);
+            self.quads.len()
+        })
+    }
+
+    /// X faces of different `x` lie on different planes, so unlike [`Merger::merge_y`] and [`Merger::merge_z`]
+    /// nothing merges along the bits of a row. Runs along y are merged upward instead, so a pillar of
+    /// identical voxels produces a single tall quad on each of its X faces.
+    fn merge_x<X, V, C>(&mut self, view: &V, context: &C, face: Face)
+    where
+        X: Copy,
+        V: MesherView<Voxel = X>,
+        C: MesherContext<Voxel = X>,
+    {
+        let [_, grid_y, grid_z] = self.config.merge_grids(face);
+
+        for z in 0..LEN {
+            for y in 0..LEN {
+                let i_2d = PaddedShape::linearize([y, z]);
+
+                let mut visible = self.visible_masks[face][i_2d];
+                let upward_visible = self.visible_masks[face][i_2d + PADDED_STRIDE_Y_2D];
+                let forward_visible = self.visible_masks[face][i_2d + PADDED_STRIDE_Z_2D];
+
+                while visible != 0 {
+                    let x = visible.trailing_zeros() as usize;
+                    visible &= visible - 1;
+
+                    let upward_i = Shape::linearize([x, 0, 0]);
+                    let forward_i = Shape::linearize([x, y, 0]);
+
+                    let pos = USizeVec3::new(x, y, z);
+                    let voxel = view.get(pos.into());
+
+                    // forward merging
+                    if self.upward_merged[upward_i] == 0
+                        && (forward_visible >> x) & 1 != 0
+                        && !(z + 1).is_multiple_of(grid_z)
+                        && context.can_merge(voxel, view.get(pos.with_z(pos.z + 1).into()))
+                    {
+                        self.forward_merged[forward_i] += 1;
+                        continue;
+                    }
+
+                    // upward merging
+                    if (upward_visible >> x) & 1 != 0
+                        && !(y + 1).is_multiple_of(grid_y)
+                        && self.forward_merged[forward_i]
+                            == self.forward_merged[forward_i + STRIDE_Y]
+                        && context.can_merge(voxel, view.get(pos.with_y(pos.y + 1).into()))
+                    {
+                        self.forward_merged[forward_i] = 0;
+                        self.upward_merged[upward_i] += 1;
+                        continue;
+                    }
+
+                    // finish
+                    {
+                        let forward_merged = self.forward_merged[forward_i] as u32;
+                        let upward_merged = self.upward_merged[upward_i] as u32;
+
+                        let x = x as u32;
+                        let y = y as u32 - upward_merged;
+                        let z = z as u32 - forward_merged;
+
+                        let w = forward_merged + 1;
+                        let h = upward_merged + 1;
+
+                        let id = context.u26_shader_id(context.into_inner(voxel).unwrap(), face);
+
+                        self.push_quad(face, [x, y, z], [w, h], id);
+                    }
+
+                    self.forward_merged[forward_i] = 0;
+                    self.upward_merged[upward_i] = 0;
+                }
+            }
+        }
+    }
+
+    fn merge_y<X, V, C>(&mut self, view: &V, context: &C, face: Face)
+    where
+        X: Copy,
+        V: MesherView<Voxel = X>,
+        C: MesherContext<Voxel = X>,
+    {
+        let [grid_x, _, grid_z] = self.config.merge_grids(face);
+
+        for z in 0..LEN {
+            for y in 0..LEN {
+                let i_2d = PaddedShape::linearize([y, z]);
+
+                let mut visible = self.visible_masks[face][i_2d];
+                let forward_visible = self.visible_masks[face][i_2d + PADDED_STRIDE_Z_2D];
+
+                while visible != 0 {
+                    let x = visible.trailing_zeros() as usize;
+
+                    let forward_i = Shape::linearize([x, y, 0]);
+
+                    let pos = USizeVec3::new(x, y, z);
+                    let voxel = view.get(pos.into());
+
+                    // forward merging
+                    if (forward_visible >> x) & 1 != 0
+                        && !(z + 1).is_multiple_of(grid_z)
+                        && context.can_merge(voxel, view.get(pos.with_z(pos.z + 1).into()))
+                    {
+                        self.forward_merged[forward_i] += 1;
+                        visible &= visible - 1;
+                        continue;
+                    }
+
+                    // rightward merging
+                    let mut i = 1;
+                    while i < (LEN - x)
+                        && (visible >> x >> i) & 1 != 0
+                        && !(x + i).is_multiple_of(grid_x)
+                        && self.forward_merged[forward_i]
+                            == self.forward_merged[forward_i + i * STRIDE_X]
+                        && context.can_merge(voxel, view.get(pos.with_x(pos.x + i).into()))
+                    {
+                        self.forward_merged[forward_i + i * STRIDE_X] = 0;
+                        i += 1;
+                    }
+                    let right_merged = i;
+                    // a run can reach the end of the row, where shifting by 64 would overflow
+                    visible &= u64::MAX.checked_shl((x + i) as u32).unwrap_or(0);
+
+                    // finish
+                    {
+                        let forward_merged = self.forward_merged[forward_i] as u32;
+
+                        let x = x as u32;
+                        let y = y as u32;
+                        let z = z as u32 - forward_merged;
+
+                        let w = right_merged as u32;
+                        let h = forward_merged + 1;
+
+                        let id = context.u26_shader_id(context.into_inner(voxel).unwrap(), face);
+
+                        self.push_quad(face, [x, y, z], [w, h], id);
+                    }
+
+                    self.forward_merged[forward_i] = 0
+                }
+            }
+        }
+    }
+
+    fn merge_z<X, V, C>(&mut self, view: &V, context: &C, face: Face)
+    where
+        X: Copy,
+        V: MesherView<Voxel = X>,
+        C: MesherContext<Voxel = X>,
+    {
+        let [grid_x, grid_y, _] = self.config.merge_grids(face);
+
+        for z in 0..LEN {
+            for y in 0..LEN {
+                let i_2d = PaddedShape::linearize([y, z]);
+
+                let mut visible = self.visible_masks[face][i_2d];
+                let upward_visible = self.visible_masks[face][i_2d + PADDED_STRIDE_Y_2D];
+
+                while visible != 0 {
+                    let x = visible.trailing_zeros() as usize;
+
+                    let upward_i = Shape::linearize([x, 0, 0]);
+
+                    let pos = USizeVec3::new(x, y, z);
+                    let voxel = view.get(pos.into());
+
+                    // upward merging
+                    if (upward_visible >> x) & 1 != 0
+                        && !(y + 1).is_multiple_of(grid_y)
+                        && context.can_merge(voxel, view.get(pos.with_y(pos.y + 1).into()))
+                    {
+                        self.upward_merged[upward_i] += 1;
+                        visible &= visible - 1;
+                        continue;
+                    }
+
+                    // rightward merging
+                    let mut i = 1;
+                    while i < (LEN - x)
+                        && (visible >> x >> i) & 1 != 0
+                        && !(x + i).is_multiple_of(grid_x)
+                        && self.upward_merged[upward_i]
+                            == self.upward_merged[upward_i + i * STRIDE_X]
+                        && context.can_merge(voxel, view.get(pos.with_x(pos.x + i).into()))
+                    {
+                        self.upward_merged[upward_i + i * STRIDE_X] = 0;
+                        i += 1;
+                    }
+                    let right_merged = i;
+                    // a run can reach the end of the row, where shifting by 64 would overflow
+                    visible &= u64::MAX.checked_shl((x + i) as u32).unwrap_or(0);
+
+                    // finish
+                    {
+                        let upward_merged = self.upward_merged[upward_i] as u32;
+
+                        let x = x as u32;
+                        let y = y as u32 - upward_merged;
+                        let z = z as u32;
+
+                        let w = right_merged as u32;
+                        let h = upward_merged + 1;
+
+                        let id = context.u26_shader_id(context.into_inner(voxel).unwrap(), face);
+
+                        self.push_quad(face, [x, y, z], [w, h], id);
+                    }
+
+                    self.upward_merged[upward_i] = 0;
+                }
+            }
+        }
+    }
+
+    /// Pushes a finished quad, splitting it along the tile grid of [`MesherConfig::tile_size`]
+    /// and dropping pieces below [`MesherConfig::min_quad_area`]
+    fn push_quad(&mut self, face: Face, xyz: [u32; 3], size: [u32; 2], id: u32) {
+        if !self.split_quads && size[0] < LEN as u32 && size[1] < LEN as u32 {
+            self.quads.push(Quad::new(xyz, size, [0; 4], id));
+            return;
+        }
+
+        let tile = self.config.tile_size as u32;
+        // sizes are stored in 6 bits, so a run spanning the whole chunk is split in two
+        let max_size = LEN as u32 - 1;
+        let [u_axis, v_axis] = face.size_axes();
+
+        let mut v = 0;
+        while v < size[1] {
+            let v_start = xyz[v_axis] + v;
+            let h = (tile - v_start % tile).min(size[1] - v).min(max_size);
+
+            let mut u = 0;
+            while u < size[0] {
+                let u_start = xyz[u_axis] + u;
+                let w = (tile - u_start % tile).min(size[0] - u).min(max_size);
+
+                let mut xyz = xyz;
+                xyz[u_axis] = u_start;
+                xyz[v_axis] = v_start;
+                if w * h >= self.config.min_quad_area {
+                    self.quads.push(Quad::new(xyz, [w, h], [0; 4], id));
+                }
+
+                u += w;
+            }
+
+            v += h;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `0` is air, every other id is a solid block
+    struct Blocks;
+
+    impl MesherContext for Blocks {
+        type Voxel = u16;
+        type InnerVoxel = u16;
+
+        fn into_inner(&self, voxel: u16) -> Option<u16> {
+            Some(voxel)
+        }
+
+        fn is_visible(&self, voxel: u16, adj_voxel: u16) -> bool {
+            voxel != 0 && adj_voxel == 0
+        }
+
+        fn can_merge(&self, voxel: u16, adj_voxel: u16) -> bool {
+            voxel == adj_voxel
+        }
+
+        fn is_open(&self, voxel: u16) -> bool {
+            voxel == 0
+        }
+
+        fn u26_shader_id(&self, voxel: u16, _face: Face) -> u32 {
+            voxel as u32
+        }
+    }
+
+    fn chunk(mut f: impl FnMut([usize; 3]) -> u16) -> Box<[u16; CUBE]> {
+        let mut voxels = Box::new([0; CUBE]);
+        for (i, voxel) in voxels.iter_mut().enumerate() {
+            *voxel = f(Shape::delinearize(i));
+        }
+        voxels
+    }
+
+    /// Views `voxels` surrounded by chunks of air
+    fn view(voxels: &[u16; CUBE]) -> impl MesherViewAdjacent<Voxel = u16> + Sync + '_ {
+        FnView {
+            get: move |offset: [usize; 3]| voxels[Shape::linearize(offset)],
+            get_adjacent: |_: [usize; 3], _: Face| Some(0),
+        }
+    }
+
+    /// Random ids in `0..ids` from a xorshift seeded with `seed`
+    fn random_chunk(mut seed: u64, ids: u64) -> Box<[u16; CUBE]> {
+        chunk(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % ids) as u16
+        })
+    }
+
+    /// Every voxel face covered by the quads of the last mesh, along with the quad's id
+    fn covered_faces(mesher: &Mesher) -> Vec<(Face, [usize; 3], u32)> {
+        let mut faces: Vec<_> = mesher
+            .iter_quads()
+            .flat_map(|(face, quad)| {
+                quad.voxel_faces(face)
+                    .map(move |xyz| (face, xyz.map(|c| c as usize), quad.id()))
+            })
+            .collect();
+        faces.sort_by_key(|&(face, xyz, _)| (face as u8, xyz));
+        faces
+    }
+
+    fn area(mesher: &Mesher, face: Face) -> u32 {
+        mesher.face_quads(face).iter().map(|quad| quad.area()).sum()
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "quad size exceeds 6 bits")]
+    fn quad_size_must_fit() {
+        Quad::new([0; 3], [64, 1], [0; 4], 0);
+    }
+
+    #[test]
+    fn full_floor() {
+        let voxels = chunk(|[_, y, _]| (y == 0) as u16);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        for face in [Face::PosY, Face::NegY] {
+            assert_eq!(area(&mesher, face), SQUARE as u32);
+        }
+        // every x row is a full run of 64
+        for face in [Face::PosZ, Face::NegZ] {
+            assert_eq!(area(&mesher, face), LEN as u32);
+        }
+    }
+
+    #[test]
+    fn build_then_merge() {
+        let voxels = demo_sphere();
+        let view = view(&voxels);
+
+        let mut mesher = Mesher::new();
+        let lens = mesher.mesh(&view, &Blocks);
+        let quads = mesher.quads.clone();
+
+        let mut split = Mesher::new();
+        split.build(&view, &Blocks);
+        assert_eq!(split.merge(&view, &Blocks), lens);
+        assert_eq!(split.quads, quads);
+    }
+
+    #[test]
+    fn occluder_of_solid_chunk() {
+        let voxels = chunk(|_| 1);
+        let view = FnView {
+            get: |offset: [usize; 3]| voxels[Shape::linearize(offset)],
+            get_adjacent: |_: [usize; 3], _: Face| None::<u16>,
+        };
+
+        let mut mesher = Mesher::new();
+        mesher.mesh_occluder(&view, &Blocks, 0);
+
+        for face in Face::ALL {
+            // a full face is split in 4 so that sizes fit 6 bits
+            assert_eq!(mesher.face_quads(face).len(), 4);
+            assert_eq!(area(&mesher, face), SQUARE as u32);
+
+            let edge = if face.is_positive() { LEN as u32 } else { 0 };
+            for quad in mesher.face_quads(face) {
+                assert_eq!(quad.aabb(face).0[face.axis()], edge);
+            }
+        }
+    }
+
+    #[test]
+    fn subregion_octant() {
+        let solid = chunk(|_| 1);
+        let mut mesher = Mesher::new();
+        mesher.mesh_subregion(&view(&solid), &Blocks, [0; 3], [32; 3], 0);
+
+        // cut faces on the positive sides, chunk boundary faces against air on the negative sides
+        for face in Face::ALL {
+            assert_eq!(area(&mesher, face), 32 * 32);
+        }
+
+        let air = chunk(|_| 0);
+        mesher.mesh_subregion(&view(&air), &Blocks, [0; 3], [32; 3], 0);
+        assert!(mesher.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn mesh_par_matches_mesh() {
+        let voxels = demo_sphere();
+        let view = view(&voxels);
+
+        let mut mesher = Mesher::new();
+        let lens = mesher.mesh(&view, &Blocks);
+
+        let mut par = Mesher::new();
+        assert_eq!(par.mesh_par(&view, &Blocks), lens);
+        assert_eq!(par.quads, mesher.quads);
+    }
+
+    #[test]
+    fn merge_grid_tiles_slab() {
+        let voxels = chunk(|[_, y, _]| (y == 0) as u16);
+        let mut mesher = Mesher::with_config(MesherConfig {
+            merge_grid: 16,
+            ..Default::default()
+        });
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let quads = mesher.face_quads(Face::PosY);
+        assert_eq!(quads.len(), 16);
+        for quad in quads {
+            assert_eq!(quad.size(), [16, 16]);
+            assert_eq!([quad.x() % 16, quad.z() % 16], [0, 0]);
+        }
+    }
+
+    #[test]
+    fn tile_size_splits_quads() {
+        let voxels = chunk(|[x, y, z]| (x < 40 && y == 0 && z == 0) as u16);
+        let mut mesher = Mesher::with_config(MesherConfig {
+            tile_size: 16,
+            ..Default::default()
+        });
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let spans: Vec<_> = mesher
+            .face_quads(Face::PosY)
+            .iter()
+            .map(|quad| quad.x()..quad.x() + quad.w())
+            .collect();
+        assert_eq!(spans, [0..16, 16..32, 32..40]);
+    }
+
+    #[test]
+    fn rightward_merge_compares_each_voxel() {
+        let voxels = chunk(|[x, y, z]| match (x, y, z) {
+            (0 | 1, 0, 0) => 1,
+            (2, 0, 0) => 2,
+            _ => 0,
+        });
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let quads: Vec<_> = mesher
+            .face_quads(Face::PosY)
+            .iter()
+            .map(|quad| (quad.x(), quad.w(), quad.id()))
+            .collect();
+        assert_eq!(quads, [(0, 2, 1), (2, 1, 2)]);
+    }
+
+    #[test]
+    fn quads_cover_exposed_faces() {
+        let mut mesher = Mesher::new();
+
+        for seed in 1..=4 {
+            let voxels = random_chunk(seed, 2 + seed % 3);
+            let view = view(&voxels);
+
+            let mut exposed = Vec::new();
+            mesher.exposed_faces(&view, &Blocks, |xyz, face| {
+                exposed.push((face, xyz, voxels[Shape::linearize(xyz)] as u32));
+            });
+            exposed.sort_by_key(|&(face, xyz, _)| (face as u8, xyz));
+
+            mesher.mesh(&view, &Blocks);
+            assert_eq!(covered_faces(&mesher), exposed, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn octahedral_matches_rotated_voxels() {
+        // asymmetric along every axis
+        let voxels = chunk(|[x, y, z]| match (x, y, z) {
+            (0..8, 0..5, 0..3) => 1 + (x + y) as u16 % 3,
+            (60.., 0, 0..2) => 4,
+            _ => 0,
+        });
+
+        for (axes, flips) in [
+            ([0, 1, 2], [true, false, false]),
+            ([0, 2, 1], [false, true, false]),
+            ([1, 0, 2], [false, false, true]),
+            ([1, 2, 0], [true, true, false]),
+            ([2, 0, 1], [true, false, true]),
+            ([2, 1, 0], [true, true, true]),
+        ] {
+            let symmetry = OctahedralSymmetry::new(axes, flips);
+
+            let mut rotated = Box::new([0; CUBE]);
+            for (i, &voxel) in voxels.iter().enumerate() {
+                let min = Shape::delinearize(i).map(|c| c as u32);
+                let a = symmetry.apply_point(min);
+                let b = symmetry.apply_point(min.map(|c| c + 1));
+                let cell = std::array::from_fn(|i| a[i].min(b[i]) as usize);
+                rotated[Shape::linearize(cell)] = voxel;
+            }
+
+            let mut mesher = Mesher::new();
+            mesher.mesh(&view(&voxels), &Blocks);
+            mesher.transform_octahedral(symmetry);
+
+            let mut expected = Mesher::new();
+            expected.mesh(&view(&rotated), &Blocks);
+
+            assert_eq!(
+                covered_faces(&mesher),
+                covered_faces(&expected),
+                "{symmetry:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn cull_enclosed_hides_sealed_hole() {
+        let voxels = chunk(|p| (p != [31; 3]) as u16);
+        let view = view(&voxels);
+
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view, &Blocks);
+        assert_eq!(mesher.surface_area(), (6 * SQUARE + 6) as f32);
+
+        mesher.config.cull_enclosed = true;
+        mesher.mesh(&view, &Blocks);
+        assert_eq!(mesher.surface_area(), (6 * SQUARE) as f32);
+    }
+
+    #[test]
+    fn demo_sphere_quad_count() {
+        let voxels = demo_sphere();
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        assert_eq!(mesher.len(), 2100);
+    }
+
+    #[test]
+    fn forks_mesh_concurrently() {
+        let a = demo_sphere();
+        let b = random_chunk(7, 3);
+
+        let mut serial = Mesher::new();
+        serial.mesh(&view(&a), &Blocks);
+        let serial_a = serial.quads.clone();
+        serial.mesh(&view(&b), &Blocks);
+        let serial_b = serial.quads.clone();
+
+        let mut fork_a = serial.fork();
+        let mut fork_b = serial.fork();
+        std::thread::scope(|s| {
+            s.spawn(|| fork_a.mesh(&view(&a), &Blocks));
+            s.spawn(|| fork_b.mesh(&view(&b), &Blocks));
+        });
+
+        assert_eq!(fork_a.quads, serial_a);
+        assert_eq!(fork_b.quads, serial_b);
+    }
+
+    #[test]
+    fn nibble_view_matches_u16() {
+        let voxels = random_chunk(3, 16);
+        let mut nibbles = [0; CUBE / 2];
+        for (i, &voxel) in voxels.iter().enumerate() {
+            nibbles[i / 2] |= (voxel as u8) << ((i & 1) * 4);
+        }
+        let air = [0; CUBE / 2];
+        let nibble_view = NibbleView {
+            main: &nibbles,
+            adj: EnumMap::from_fn(|_| Some(&air)),
+        };
+        let widened = FnView {
+            get: |offset: [usize; 3]| nibble_view.get(offset) as u16,
+            get_adjacent: |offset: [usize; 3], face: Face| {
+                nibble_view.get_adjacent(offset, face).map(u16::from)
+            },
+        };
+
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+        let mut nibble_mesher = Mesher::new();
+        nibble_mesher.mesh(&widened, &Blocks);
+
+        assert_eq!(nibble_mesher.quads, mesher.quads);
+    }
+
+    #[test]
+    fn debug_visible_layer_of_one_voxel() {
+        let voxels = chunk(|p| (p == [1, 2, 0]) as u16);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let mut expected = String::new();
+        for y in 0..LEN {
+            for x in 0..LEN {
+                expected.push(if [x, y] == [1, 2] { '#' } else { '.' });
+            }
+            expected.push('\n');
+        }
+        assert_eq!(mesher.debug_visible_layer(Face::PosX, 0), expected);
+        assert!(!mesher.debug_visible_layer(Face::PosX, 1).contains('#'));
+    }
+
+    #[test]
+    fn checkerboard_within_max_quads() {
+        let voxels = chunk(|[x, y, z]| ((x + y + z) % 2) as u16);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        // every face of every solid voxel is its own quad
+        assert_eq!(mesher.len(), 6 * CUBE / 2);
+        assert!(mesher.len() <= max_quads());
+    }
+
+    #[test]
+    fn face_bytes_round_trip() {
+        let voxels = demo_sphere();
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        for face in Face::ALL {
+            let bytes = mesher.face_bytes(face);
+            assert_eq!(bytes.len(), 8 * mesher.face_quads(face).len());
+            assert_eq!(Quad::cast_slice(bytes), Ok(mesher.face_quads(face)));
+        }
+    }
+
+    #[test]
+    fn column_masks() {
+        let quad = Quad::new([3, 0, 0], [4, 2], [0; 4], 0);
+        assert_eq!(quad.column_mask(Face::PosY), 0b1111 << 3);
+        assert_eq!(quad.column_mask(Face::NegZ), 0b1111 << 3);
+        assert_eq!(quad.column_mask(Face::PosX), 1 << 3);
+
+        let quad = Quad::new([60, 0, 0], [3, 1], [0; 4], 0);
+        assert_eq!(quad.column_mask(Face::PosZ), 0b111 << 60);
+    }
+
+    #[test]
+    fn mesh_culled_from_pos_x() {
+        let voxels = demo_sphere();
+        let view = view(&voxels);
+
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view, &Blocks);
+        let mut culled = Mesher::new();
+        culled.mesh_culled(&view, &Blocks, [100.0, 32.0, 32.0]);
+
+        assert!(culled.face_quads(Face::NegX).is_empty());
+        for face in [Face::PosX, Face::PosY, Face::NegY, Face::PosZ, Face::NegZ] {
+            assert_eq!(culled.face_quads(face), mesher.face_quads(face));
+        }
+    }
+
+    #[test]
+    fn aabbs_of_one_voxel() {
+        let voxels = chunk(|p| (p == [1, 2, 3]) as u16);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let aabbs: Vec<_> = mesher.aabbs(0.0).collect();
+        assert_eq!(
+            aabbs,
+            [
+                ([2.0, 2.0, 3.0], [2.0, 3.0, 4.0]),
+                ([1.0, 2.0, 3.0], [1.0, 3.0, 4.0]),
+                ([1.0, 3.0, 3.0], [2.0, 3.0, 4.0]),
+                ([1.0, 2.0, 3.0], [2.0, 2.0, 4.0]),
+                ([1.0, 2.0, 4.0], [2.0, 3.0, 4.0]),
+                ([1.0, 2.0, 3.0], [2.0, 3.0, 3.0]),
+            ]
+        );
+
+        let (min, max) = mesher.aabbs(0.5).next().unwrap();
+        assert_eq!((min[0], max[0]), (1.75, 2.25));
+    }
+
+    #[test]
+    fn pillar_x_faces_merge_along_y() {
+        let voxels = chunk(|[x, y, z]| (x == 5 && y < 10 && z == 7) as u16);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let quads = mesher.face_quads(Face::PosX);
+        assert_eq!(quads.len(), 1);
+        assert_eq!(quads[0].xyz(), [5, 0, 7]);
+        assert_eq!(quads[0].size(), [1, 10]);
+    }
+
+    #[test]
+    fn u8_accessors_at_max_coordinates() {
+        for quad in [
+            Quad::new([63; 3], [63, 63], [0; 4], 0),
+            Quad::new([0, 31, 63], [1, 63], [0; 4], 0),
+        ] {
+            assert_eq!(quad.xyz_u8(), quad.xyz().map(|c| c as u8));
+            assert_eq!(quad.size_u8(), quad.size().map(|c| c as u8));
+        }
+
+        // a run of 64 is split, so the widest quad the mesher emits is 63
+        let voxels = chunk(|[_, y, z]| (y == 0 && z == 0) as u16);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+        for (_, quad) in mesher.iter_quads() {
+            assert_eq!(quad.xyz_u8(), quad.xyz().map(|c| c as u8));
+            assert_eq!(quad.size_u8(), quad.size().map(|c| c as u8));
+        }
+        assert!(
+            mesher
+                .face_quads(Face::PosY)
+                .iter()
+                .any(|quad| quad.size_u8() == [63, 1])
+        );
+    }
+
+    #[test]
+    fn bounds_of_two_voxels() {
+        let mut mesher = Mesher::new();
+        assert_eq!(mesher.bounds(), None);
+
+        let voxels = chunk(|p| (p == [1, 2, 3] || p == [10, 5, 7]) as u16);
+        mesher.mesh(&view(&voxels), &Blocks);
+        assert_eq!(mesher.bounds(), Some(([1, 2, 3], [11, 6, 8])));
+    }
+
+    #[test]
+    fn is_face_visible_hides_tops() {
+        /// [`Blocks`] without any `PosY` faces
+        struct NoTops;
+
+        impl MesherContext for NoTops {
+            type Voxel = u16;
+            type InnerVoxel = u16;
+
+            fn into_inner(&self, voxel: u16) -> Option<u16> {
+                Some(voxel)
+            }
+
+            fn is_visible(&self, voxel: u16, adj_voxel: u16) -> bool {
+                Blocks.is_visible(voxel, adj_voxel)
+            }
+
+            fn is_face_visible(&self, voxel: u16, adj_voxel: u16, face: Face) -> bool {
+                face != Face::PosY && self.is_visible(voxel, adj_voxel)
+            }
+
+            fn can_merge(&self, voxel: u16, adj_voxel: u16) -> bool {
+                voxel == adj_voxel
+            }
+
+            fn u26_shader_id(&self, voxel: u16, _face: Face) -> u32 {
+                voxel as u32
+            }
+        }
+
+        let voxels = demo_sphere();
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &NoTops);
+
+        assert_eq!(area(&mesher, Face::PosY), 0);
+        assert_ne!(area(&mesher, Face::NegY), 0);
+    }
+
+    #[test]
+    fn group_by_material_ranges() {
+        let voxels = chunk(|[x, y, z]| match (x, y, z) {
+            (0 | 4, 0, 0) => 2,
+            (2, 0, 0) => 1,
+            _ => 0,
+        });
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let groups = mesher.group_by_material();
+        assert_eq!(groups[Face::PosY], [(1, 0..1), (2, 1..3)]);
+
+        let ids: Vec<_> = mesher
+            .face_quads(Face::PosY)
+            .iter()
+            .map(|q| q.id())
+            .collect();
+        assert_eq!(ids, [1, 2, 2]);
+    }
+
+    #[test]
+    fn sort_faces_by_keeps_face_ranges() {
+        let voxels = random_chunk(5, 4);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let mut before = mesher.flatten();
+        mesher.sort_faces_by(|_, quad| std::cmp::Reverse(quad.id()));
+        let mut after = mesher.flatten();
+
+        for face in Face::ALL {
+            let ids: Vec<_> = mesher.face_quads(face).iter().map(|q| q.id()).collect();
+            assert!(ids.is_sorted_by(|a, b| a >= b));
+        }
+
+        let key = |&(face, quad): &(Face, Quad)| (face as u8, quad.xyz(), quad.size());
+        before.sort_by_key(key);
+        after.sort_by_key(key);
+        assert_eq!(after, before);
+    }
+
+    #[test]
+    fn surface_voxel_counts_of_cube() {
+        let voxels = chunk(|p| p.iter().all(|&c| (10..14).contains(&c)) as u16);
+        let counts = Mesher::new().surface_voxel_counts(&view(&voxels), &Blocks);
+
+        assert_eq!(counts[&1], (4 * 4 * 4 - 2 * 2 * 2, 2 * 2 * 2));
+        assert_eq!(counts[&0], (0, CUBE - 4 * 4 * 4));
+    }
+
+    #[test]
+    fn raycast_hits_nearest_face() {
+        let voxels = chunk(|p| (p == [1, 2, 3]) as u16);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let (face, quad, t) = mesher.raycast([10.0, 2.5, 3.5], [-1.0, 0.0, 0.0]).unwrap();
+        assert_eq!((face, quad.xyz(), t), (Face::PosX, [1, 2, 3], 8.0));
+
+        assert_eq!(mesher.raycast([10.0, 2.5, 3.5], [1.0, 0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn density_view_matches_threshold() {
+        let mut density = [0; CUBE];
+        for (i, d) in density.iter_mut().enumerate() {
+            let [x, y, _] = Shape::delinearize(i);
+            *d = y as i8 - 20 + (x % 3) as i8;
+        }
+        let density_view = DensityView::new(&density, 0);
+        let widened = FnView {
+            get: |offset: [usize; 3]| density_view.get(offset) as u16,
+            get_adjacent: |offset: [usize; 3], face: Face| {
+                density_view.get_adjacent(offset, face).map(u16::from)
+            },
+        };
+
+        let voxels = chunk(|[x, y, _]| (y + x % 3 >= 20) as u16);
+        let solid = FnView {
+            get: |offset: [usize; 3]| voxels[Shape::linearize(offset)],
+            get_adjacent: |_: [usize; 3], _: Face| None,
+        };
+
+        let mut mesher = Mesher::new();
+        mesher.mesh(&solid, &Blocks);
+        let mut density_mesher = Mesher::new();
+        density_mesher.mesh(&widened, &Blocks);
+
+        assert_eq!(density_mesher.quads, mesher.quads);
+    }
+
+    #[test]
+    fn ao_brightness_per_corner() {
+        let quad = Quad::new([0; 3], [1, 1], [0, 1, 2, 3], 0);
+        let linear = [1.0, 0.75, 0.5, 0.25];
+        let steep = [1.0, 0.5, 0.25, 0.0];
+
+        for corner in 0..4 {
+            assert_eq!(quad.ao_brightness(corner, linear), linear[corner]);
+            assert_eq!(quad.ao_brightness(corner, steep), steep[corner]);
+        }
+    }
+
+    #[test]
+    fn fn_view_over_interleaved_voxels() {
+        let voxels = random_chunk(11, 3);
+        let interleaved: Vec<(u16, u16)> = voxels.iter().map(|&v| (v, 0xBEEF)).collect();
+        let interleaved_view = FnView {
+            get: |offset: [usize; 3]| interleaved[Shape::linearize(offset)].0,
+            get_adjacent: |_: [usize; 3], _: Face| Some(0),
+        };
+
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+        let mut interleaved_mesher = Mesher::new();
+        interleaved_mesher.mesh(&interleaved_view, &Blocks);
+
+        assert_eq!(interleaved_mesher.quads, mesher.quads);
+    }
+
+    #[test]
+    fn min_quad_area_drops_small_quads() {
+        let voxels = random_chunk(13, 2);
+        let view = view(&voxels);
+
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view, &Blocks);
+        let all = mesher.len();
+
+        mesher.config.min_quad_area = 2;
+        mesher.mesh(&view, &Blocks);
+        assert!(mesher.len() < all);
+        assert!(mesher.quads.iter().all(|quad| quad.area() >= 2));
+    }
+
+    #[test]
+    fn exposed_faces_match_visible_bits() {
+        let voxels = demo_sphere();
+        let mut mesher = Mesher::new();
+
+        let mut count = 0;
+        mesher.exposed_faces(&view(&voxels), &Blocks, |_, _| count += 1);
+
+        let visible: u32 = mesher
+            .take_visible()
+            .values()
+            .flat_map(|mask| mask.iter())
+            .map(|row| row.count_ones())
+            .sum();
+        assert_eq!(count, visible as usize);
+    }
+
+    #[test]
+    fn non_greedy_quad_per_face() {
+        let voxels = demo_sphere();
+        let mut mesher = Mesher::with_config(MesherConfig {
+            greedy: false,
+            ..Default::default()
+        });
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let visible: u32 = mesher
+            .take_visible()
+            .values()
+            .flat_map(|mask| mask.iter())
+            .map(|row| row.count_ones())
+            .sum();
+        assert_eq!(mesher.len(), visible as usize);
+        assert!(mesher.quads.iter().all(|quad| quad.size() == [1, 1]));
+    }
+
+    #[test]
+    fn subregions_of_shared_buffer() {
+        let voxels: std::sync::Arc<[u16; CUBE]> = demo_sphere().into();
+        let octants: Vec<[usize; 3]> = (0..8)
+            .map(|i| [i & 1, (i >> 1) & 1, i >> 2].map(|c| c * LEN / 2))
+            .collect();
+
+        let threads: Vec<_> = octants
+            .iter()
+            .map(|&min| {
+                let voxels = voxels.clone();
+                std::thread::spawn(move || {
+                    let mut mesher = Mesher::new();
+                    let max = min.map(|c| c + LEN / 2);
+                    mesher.mesh_subregion(&view(&voxels), &Blocks, min, max, 0);
+                    mesher.quads
+                })
+            })
+            .collect();
+
+        let mut mesher = Mesher::new();
+        for (thread, &min) in threads.into_iter().zip(&octants) {
+            let max = min.map(|c| c + LEN / 2);
+            mesher.mesh_subregion(&view(&voxels), &Blocks, min, max, 0);
+            assert_eq!(thread.join().unwrap(), mesher.quads);
+        }
+    }
+
+    #[test]
+    fn merge_axes_strips() {
+        let voxels = chunk(|[x, y, z]| (x < 32 && y == 0 && z < 8) as u16);
+        let mut mesher = Mesher::with_config(MesherConfig {
+            merge_axes: [true, false, false],
+            ..Default::default()
+        });
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let quads = mesher.face_quads(Face::PosY);
+        assert_eq!(quads.len(), 8);
+        assert!(quads.iter().all(|quad| quad.size() == [32, 1]));
+    }
+
+    #[test]
+    fn wrap_culls_boundary_faces() {
+        let voxels = chunk(|[_, y, z]| (y == 0 && z == 0) as u16);
+        let view = view(&voxels);
+
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view, &Blocks);
+        assert_eq!(area(&mesher, Face::PosX) + area(&mesher, Face::NegX), 2);
+
+        mesher.config.wrap = [true, false, false];
+        mesher.mesh(&view, &Blocks);
+        assert_eq!(area(&mesher, Face::PosX) + area(&mesher, Face::NegX), 0);
+    }
+
+    #[test]
+    fn wrap_into_region_outside() {
+        let solid = chunk(|_| 1);
+        let mut mesher = Mesher::new();
+        mesher.config.wrap = [true, false, false];
+        mesher.mesh_subregion(&view(&solid), &Blocks, [0; 3], [32, 64, 64], 0);
+
+        // x = 0 wraps around to x = 63, which is outside the region
+        assert_eq!(area(&mesher, Face::NegX), 64 * 64);
+    }
+
+    #[test]
+    fn iter_sorted_by_axis_is_monotonic() {
+        let voxels = demo_sphere();
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        for axis in 0..3 {
+            let sorted: Vec<_> = mesher.iter_sorted_by_axis(axis).collect();
+            assert_eq!(sorted.len(), mesher.len());
+            assert!(sorted.is_sorted_by_key(|&(face, quad)| quad.aabb(face).0[axis]));
+        }
+    }
+
+    #[test]
+    fn shrink_to_fit_releases_quads() {
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&random_chunk(17, 2)), &Blocks);
+        let capacity = mesher.quads.capacity();
+
+        mesher.mesh(&view(&chunk(|p| (p == [0; 3]) as u16)), &Blocks);
+        mesher.shrink_to_fit();
+        assert!(mesher.quads.capacity() < capacity);
+        assert_eq!(mesher.len(), 6);
+    }
+
+    #[test]
+    fn flatten_tags_faces() {
+        let voxels = demo_sphere();
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let flat = mesher.flatten();
+        assert_eq!(flat.len(), mesher.len());
+        for face in Face::ALL {
+            let range = mesher.face_range(face);
+            assert!(flat[range.clone()].iter().all(|&(f, _)| f == face));
+            let quads: Vec<_> = flat[range].iter().map(|&(_, quad)| quad).collect();
+            assert_eq!(quads, mesher.face_quads(face));
+        }
+
+        let mut out = vec![(Face::PosX, Quad::new([0; 3], [1, 1], [0; 4], 0))];
+        mesher.flatten_into(&mut out);
+        assert_eq!(out[1..], flat);
+    }
+
+    #[test]
+    fn quad_at_finds_merged_quad() {
+        let voxels = chunk(|[x, y, z]| (x < 3 && y == 0 && z == 0) as u16);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let quad = mesher.quad_at(Face::PosY, [1, 0, 0]).unwrap();
+        assert_eq!((quad.xyz(), quad.size()), ([0, 0, 0], [3, 1]));
+        assert_eq!(
+            mesher.quad_at(Face::PosY, [1, 0, 0]),
+            mesher.quad_at(Face::PosY, [2, 0, 0])
+        );
+
+        assert!(mesher.quad_at(Face::NegX, [0, 0, 0]).is_some());
+        assert!(mesher.quad_at(Face::NegX, [1, 0, 0]).is_none());
+        assert!(mesher.quad_at(Face::PosY, [3, 0, 0]).is_none());
+    }
+
+    #[test]
+    fn mesh_filtered_drops_and_edits() {
+        let voxels = demo_sphere();
+        let view = view(&voxels);
+
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view, &Blocks);
+        let mut filtered = Mesher::new();
+        filtered.mesh_filtered(&view, &Blocks, |face, quad| {
+            *quad = Quad::new(quad.xyz(), quad.size(), quad.ao(), 7);
+            face != Face::PosY
+        });
+
+        assert!(filtered.face_quads(Face::PosY).is_empty());
+        for face in Face::ALL.into_iter().filter(|&face| face != Face::PosY) {
+            let expected = mesher
+                .face_quads(face)
+                .iter()
+                .map(|q| (q.xyz(), q.size(), 7));
+            let quads = filtered
+                .face_quads(face)
+                .iter()
+                .map(|q| (q.xyz(), q.size(), q.id()));
+            assert!(quads.eq(expected));
+        }
+    }
+
+    #[test]
+    fn take_visible_matches_reference() {
+        let voxels = random_chunk(19, 3);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+        let visible = mesher.take_visible();
+
+        for (i, &voxel) in voxels.iter().enumerate() {
+            let [x, y, z] = Shape::delinearize(i);
+            for face in Face::ALL {
+                let adj = USizeVec3::new(x, y, z)
+                    .as_ivec3()
+                    .wrapping_add(face.to_ivec3());
+                let adj_voxel = if adj.min_element() < 0 || adj.max_element() >= LEN as i32 {
+                    0
+                } else {
+                    voxels[Shape::linearize(adj.as_usizevec3().into())]
+                };
+
+                let expected = voxel != 0 && adj_voxel == 0;
+                assert_eq!((visible[face][y + z * LEN] >> x) & 1 != 0, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn surface_area_of_solid_chunk() {
+        let voxels = chunk(|_| 1);
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        assert_eq!(mesher.surface_area(), (6 * SQUARE) as f32);
+    }
+
+    #[test]
+    fn origin_id_round_trip() {
+        for xyz in [[0, 0, 0], [3, 4, 5], [63, 0, 17], [63, 63, 63]] {
+            let quad = Quad::new(xyz, [1, 1], [0; 4], 0);
+            let index = quad.origin_id() as usize;
+            assert_eq!(index, Shape::linearize(xyz.map(|c| c as usize)));
+            assert_eq!(Shape::delinearize(index).map(|c| c as u32), xyz);
+        }
+    }
+
+    #[test]
+    fn quad_adjacency_shares_edges() {
+        let voxels = chunk(|[x, y, z]| match (x, y, z) {
+            (0 | 3, 0, 0) => 1,
+            (1, 0, 0) => 2,
+            _ => 0,
+        });
+        let mut mesher = Mesher::new();
+        mesher.mesh(&view(&voxels), &Blocks);
+        let adjacency = mesher.quad_adjacency();
+
+        let start = mesher.face_range(Face::PosY).start;
+        let xs: Vec<_> = mesher
+            .face_quads(Face::PosY)
+            .iter()
+            .map(|q| q.x())
+            .collect();
+        assert_eq!(xs, [0, 1, 3]);
+        assert_eq!(adjacency[start], [start + 1]);
+        assert_eq!(adjacency[start + 1], [start]);
+        assert!(adjacency[start + 2].is_empty());
+    }
+
+    #[test]
+    fn merge_faces_disables_one_face() {
+        let voxels = chunk(|[x, y, z]| (x < 8 && y == 0 && z < 8) as u16);
+        let mut config = MesherConfig::default();
+        config.merge_faces[Face::PosY] = false;
+        let mut mesher = Mesher::with_config(config);
+        mesher.mesh(&view(&voxels), &Blocks);
+
+        let visible: u32 = mesher.take_visible()[Face::PosY]
+            .iter()
+            .map(|row| row.count_ones())
+            .sum();
+        assert_eq!(mesher.face_quads(Face::PosY).len(), visible as usize);
+        assert_eq!(mesher.face_quads(Face::PosY).len(), 64);
+        assert_eq!(mesher.face_quads(Face::NegY).len(), 1);
+    }
+
+    #[test]
+    fn voxel_faces_of_quad() {
+        let quad = Quad::new([1, 2, 3], [3, 2], [0; 4], 0);
+
+        let faces: Vec<_> = quad.voxel_faces(Face::PosZ).collect();
+        assert_eq!(
+            faces,
+            [
+                [1, 2, 3],
+                [2, 2, 3],
+                [3, 2, 3],
+                [1, 3, 3],
+                [2, 3, 3],
+                [3, 3, 3]
+            ]
+        );
+
+        // w along z and h along y on X faces
+        let faces: Vec<_> = quad.voxel_faces(Face::NegX).collect();
+        assert_eq!(faces.len(), 6);
+        assert!(
+            faces
+                .iter()
+                .all(|&[x, y, z]| x == 1 && (2..4).contains(&y) && (3..6).contains(&z))
+        );
+    }
 }
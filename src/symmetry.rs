@@ -0,0 +1,89 @@
+use crate::{Face, LEN, Quad};
+
+/// One of the 48 symmetries of a cube: a permutation of the axes followed by optional mirroring of each axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct OctahedralSymmetry {
+    /// The new axis `i` is taken from the old axis `axes[i]`
+    axes: [usize; 3],
+    /// Whether the new axis `i` is mirrored within the chunk
+    flips: [bool; 3],
+}
+
+impl OctahedralSymmetry {
+    pub const IDENTITY: Self = Self::new([0, 1, 2], [false; 3]);
+
+    /// # Panics
+    /// If `axes` isn't a permutation of `[0, 1, 2]`
+    pub const fn new(axes: [usize; 3], flips: [bool; 3]) -> Self {
+        assert!(
+            axes[0] < 3 && axes[1] < 3 && axes[2] < 3,
+            "axes must be a permutation of [0, 1, 2]"
+        );
+        assert!(
+            axes[0] != axes[1] && axes[1] != axes[2] && axes[0] != axes[2],
+            "axes must be a permutation of [0, 1, 2]"
+        );
+        Self { axes, flips }
+    }
+
+    /// Maps a corner position in `0..=LEN`
+    pub const fn apply_point(self, point: [u32; 3]) -> [u32; 3] {
+        let mut out = [0; 3];
+        let mut i = 0;
+        while i < 3 {
+            let c = point[self.axes[i]];
+            out[i] = if self.flips[i] { LEN as u32 - c } else { c };
+            i += 1;
+        }
+        out
+    }
+
+    pub const fn apply_face(self, face: Face) -> Face {
+        let axis = face.axis();
+        let mut new_axis = 0;
+        while self.axes[new_axis] != axis {
+            new_axis += 1;
+        }
+
+        match (new_axis, face.is_positive() != self.flips[new_axis]) {
+            (0, true) => Face::PosX,
+            (0, false) => Face::NegX,
+            (1, true) => Face::PosY,
+            (1, false) => Face::NegY,
+            (2, true) => Face::PosZ,
+            _ => Face::NegZ,
+        }
+    }
+
+    /// Maps a quad on `face` to its new face and position, the id and ambient occlusion are kept as is
+    pub const fn apply(self, face: Face, quad: Quad) -> (Face, Quad) {
+        let (min, max) = quad.aabb(face);
+        let a = self.apply_point(min);
+        let b = self.apply_point(max);
+
+        let mut min = [0; 3];
+        let mut max = [0; 3];
+        let mut i = 0;
+        while i < 3 {
+            (min[i], max[i]) = if a[i] < b[i] {
+                (a[i], b[i])
+            } else {
+                (b[i], a[i])
+            };
+            i += 1;
+        }
+
+        let new_face = self.apply_face(face);
+        let axis = new_face.axis();
+        let [u_axis, v_axis] = new_face.size_axes();
+
+        let mut xyz = min;
+        if new_face.is_positive() {
+            xyz[axis] -= 1;
+        }
+
+        let size = [max[u_axis] - min[u_axis], max[v_axis] - min[v_axis]];
+
+        (new_face, Quad::new(xyz, size, quad.ao(), quad.id()))
+    }
+}
@@ -1,8 +1,9 @@
 use std::fmt::Debug;
 
-use bytemuck::{Pod, Zeroable};
+use bytemuck::{Pod, PodCastError, Zeroable};
 use enum_map::Enum;
 use glam::IVec3;
+use ndshape::ConstShape as _;
 
 const MASK_26: u32 = (1 << 26) - 1;
 const MASK_6: u32 = (1 << 6) - 1;
@@ -21,6 +22,7 @@ const SHIFT_AO_D: u32 = 30;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[derive(Enum)]
+#[repr(u8)]
 pub enum Face {
     PosX = 0,
     NegX = 1,
@@ -50,6 +52,29 @@ impl Face {
             Self::NegZ => IVec3::NEG_Z,
         }
     }
+
+    /// The axis this face points along, `0` = x, `1` = y, `2` = z
+    pub const fn axis(self) -> usize {
+        match self {
+            Self::PosX | Self::NegX => 0,
+            Self::PosY | Self::NegY => 1,
+            Self::PosZ | Self::NegZ => 2,
+        }
+    }
+
+    /// Whether this face points towards the positive end of its axis
+    pub const fn is_positive(self) -> bool {
+        matches!(self, Self::PosX | Self::PosY | Self::PosZ)
+    }
+
+    /// The axes a [`Quad`]'s width and height extend along on this face
+    pub const fn size_axes(self) -> [usize; 2] {
+        match self {
+            Self::PosX | Self::NegX => [2, 1],
+            Self::PosY | Self::NegY => [0, 2],
+            Self::PosZ | Self::NegZ => [0, 1],
+        }
+    }
 }
 
 /// # Contents
@@ -82,7 +107,16 @@ impl Debug for Quad {
 }
 
 impl Quad {
+    /// Every position and size must be below [`LEN`](crate::LEN) to fit its 6 bits, checked in debug builds.
     pub const fn new(xyz: [u32; 3], size: [u32; 2], ao: [u32; 4], id: u32) -> Self {
+        debug_assert!(
+            xyz[0] <= MASK_6 && xyz[1] <= MASK_6 && xyz[2] <= MASK_6,
+            "quad position exceeds 6 bits"
+        );
+        debug_assert!(
+            size[0] <= MASK_6 && size[1] <= MASK_6,
+            "quad size exceeds 6 bits"
+        );
         Self([
             ((xyz[0] & MASK_6) << SHIFT_X)
                 | ((xyz[1] & MASK_6) << SHIFT_Y)
@@ -145,15 +179,99 @@ impl Quad {
         [self.w(), self.h()]
     }
 
+    /// Maps the ambient occlusion level of `corner` through `curve`, e.g. `[1.0, 0.8, 0.6, 0.4]` for 0 to 3 occluders.
+    ///
+    /// The mesher always writes an AO of `0`, so this only varies for quads whose AO was set afterwards.
+    ///
+    /// # Panics
+    /// If `corner` isn't below `4`
+    pub const fn ao_brightness(self, corner: usize, curve: [f32; 4]) -> f32 {
+        curve[self.ao()[corner] as usize]
+    }
+
+    /// Same as [`Quad::xyz`] but as bytes, every 6 bit field fits in a `u8`
+    pub const fn xyz_u8(self) -> [u8; 3] {
+        [self.x() as u8, self.y() as u8, self.z() as u8]
+    }
+
+    /// Same as [`Quad::size`] but as bytes, every 6 bit field fits in a `u8`
+    pub const fn size_u8(self) -> [u8; 2] {
+        [self.w() as u8, self.h() as u8]
+    }
+
+    pub const fn area(self) -> u32 {
+        self.w() * self.h()
+    }
+
     pub const fn ao(self) -> [u32; 4] {
         [self.ao_a(), self.ao_b(), self.ao_c(), self.ao_d()]
     }
+
+    /// The integer corners of the area this quad covers on `face`, `min` and `max` are equal along the face's axis
+    pub const fn aabb(self, face: Face) -> ([u32; 3], [u32; 3]) {
+        let axis = face.axis();
+        let [u_axis, v_axis] = face.size_axes();
+
+        let mut min = self.xyz();
+        if face.is_positive() {
+            min[axis] += 1;
+        }
+
+        let mut max = min;
+        max[u_axis] += self.w();
+        max[v_axis] += self.h();
+
+        (min, max)
+    }
+
+    /// The position of every voxel whose `face` this quad covers, the inverse of merging for one quad
+    pub fn voxel_faces(self, face: Face) -> impl Iterator<Item = [u32; 3]> {
+        let [u_axis, v_axis] = face.size_axes();
+        (0..self.h()).flat_map(move |v| {
+            (0..self.w()).map(move |u| {
+                let mut xyz = self.xyz();
+                xyz[u_axis] += u;
+                xyz[v_axis] += v;
+                xyz
+            })
+        })
+    }
+
+    /// The x bits this quad covers in a `u64` row of voxels, like the ones used while meshing.
+    ///
+    /// X faces only cover the bit of their own `x`, while Y and Z faces span `w` bits from `x`.
+    pub const fn column_mask(self, face: Face) -> u64 {
+        let span = match face {
+            Face::PosX | Face::NegX => 1,
+            _ => (1 << self.w()) - 1,
+        };
+        span << self.x()
+    }
+
+    /// The index of the voxel at this quad's position in [`Shape`](crate::Shape) order, e.g. as a GPU picking id.
+    ///
+    /// Fits in 18 bits.
+    pub fn origin_id(self) -> u32 {
+        crate::Shape::linearize(self.xyz().map(|c| c as usize)) as u32
+    }
+
+    /// Reinterprets bytes, e.g. from a memory mapped file, as quads without copying.
+    ///
+    /// Fails if `bytes` isn't aligned to 4 bytes or its length isn't a multiple of 8.
+    pub fn cast_slice(bytes: &[u8]) -> Result<&[Self], PodCastError> {
+        bytemuck::try_cast_slice(bytes)
+    }
 }
 
 /// This should be infallible and is restricted to within the meshed chunk
 ///
 /// ## Speed
 /// I suggest you `#[inline]` these functions
+///
+/// ## Threads
+/// Voxels are only ever read through `&self`, the mesher never mutates them.
+/// A view over a shared buffer (e.g. an `Arc<[u16; CUBE]>`) can be meshed from many threads at once,
+/// each with its own [`Mesher`](crate::Mesher), like disjoint regions with [`Mesher::mesh_subregion`](crate::Mesher::mesh_subregion).
 pub trait MesherView {
     type Voxel;
 
@@ -175,11 +293,41 @@ pub trait MesherContext {
     type Voxel;
     type InnerVoxel;
 
+    /// Returns `None` for voxels that neither emit faces nor reveal their neighbors' faces.
+    ///
+    /// No value is reserved by the mesher, so any sentinel (`0`, `u16::MAX`, ...) can represent these voxels,
+    /// while air that should reveal neighbors is an inner voxel that is never visible itself.
+    #[allow(clippy::wrong_self_convention)]
     fn into_inner(&self, voxel: Self::Voxel) -> Option<Self::InnerVoxel>;
 
+    /// Whether the face of `voxel` touching `adj_voxel` is emitted.
+    ///
+    /// Both voxels are given so culling can depend on the pair, e.g. hiding the boundary between two different transparent voxels.
     fn is_visible(&self, voxel: Self::InnerVoxel, adj_voxel: Self::InnerVoxel) -> bool;
 
+    /// Same as [`MesherContext::is_visible`] but also given the `face` of `voxel` touching `adj_voxel`,
+    /// e.g. for leaves that cull their sides against each other but always show their top.
+    ///
+    /// This is what the mesher calls, by default it ignores `face`.
+    // inlined early so the mesher's loop sees through to `is_visible`, like before this method existed
+    #[inline(always)]
+    fn is_face_visible(
+        &self,
+        voxel: Self::InnerVoxel,
+        adj_voxel: Self::InnerVoxel,
+        _face: Face,
+    ) -> bool {
+        self.is_visible(voxel, adj_voxel)
+    }
+
     fn can_merge(&self, voxel: Self::Voxel, adj_voxel: Self::Voxel) -> bool;
 
+    /// Whether `voxel` can be seen through, like air or glass.
+    ///
+    /// Only used to find enclosed voids when [`MesherConfig::cull_enclosed`](crate::MesherConfig::cull_enclosed) is set.
+    fn is_open(&self, _voxel: Self::InnerVoxel) -> bool {
+        true
+    }
+
     fn u26_shader_id(&self, voxel: Self::InnerVoxel, face: Face) -> u32;
 }
@@ -0,0 +1,120 @@
+use enum_map::EnumMap;
+use ndshape::ConstShape as _;
+
+use crate::{CUBE, Face, MesherView, MesherViewAdjacent, Shape};
+
+/// A chunk of 4 bit voxels, packed 2 per byte
+///
+/// The voxel at index `i` is stored in the low nibble of byte `i / 2` when `i` is even and in the high nibble otherwise.
+pub type NibbleChunk = [u8; CUBE / 2];
+
+/// A [`MesherView`] over [`NibbleChunk`]s, for worlds with at most 16 voxel types.
+///
+/// Halves the memory of a `[u8; CUBE]` chunk, nibbles are unpacked on the fly.
+#[derive(Debug, Clone, Copy)]
+pub struct NibbleView<'a> {
+    pub main: &'a NibbleChunk,
+    pub adj: EnumMap<Face, Option<&'a NibbleChunk>>,
+}
+
+impl<'a> NibbleView<'a> {
+    /// Creates a view without any adjacent chunks
+    pub fn new(main: &'a NibbleChunk) -> Self {
+        Self {
+            main,
+            adj: EnumMap::default(),
+        }
+    }
+}
+
+#[inline]
+fn get_nibble(chunk: &NibbleChunk, offset: [usize; 3]) -> u8 {
+    let index = Shape::linearize(offset);
+    (chunk[index / 2] >> ((index & 1) * 4)) & 0xF
+}
+
+impl MesherView for NibbleView<'_> {
+    type Voxel = u8;
+
+    #[inline]
+    fn get(&self, offset: [usize; 3]) -> Self::Voxel {
+        get_nibble(self.main, offset)
+    }
+}
+
+impl MesherViewAdjacent for NibbleView<'_> {
+    #[inline]
+    fn get_adjacent(&self, offset: [usize; 3], face: Face) -> Option<Self::Voxel> {
+        self.adj[face].map(|c| get_nibble(c, offset))
+    }
+}
+
+/// A [`MesherView`] over a density field, where voxels are solid (`true`) when their density is at least `threshold`.
+///
+/// Avoids materializing a separate voxel buffer for noise driven terrain.
+#[derive(Debug, Clone, Copy)]
+pub struct DensityView<'a> {
+    pub main: &'a [i8; CUBE],
+    pub adj: EnumMap<Face, Option<&'a [i8; CUBE]>>,
+    pub threshold: i8,
+}
+
+impl<'a> DensityView<'a> {
+    /// Creates a view without any adjacent chunks
+    pub fn new(main: &'a [i8; CUBE], threshold: i8) -> Self {
+        Self {
+            main,
+            adj: EnumMap::default(),
+            threshold,
+        }
+    }
+}
+
+impl MesherView for DensityView<'_> {
+    type Voxel = bool;
+
+    #[inline]
+    fn get(&self, offset: [usize; 3]) -> Self::Voxel {
+        self.main[Shape::linearize(offset)] >= self.threshold
+    }
+}
+
+impl MesherViewAdjacent for DensityView<'_> {
+    #[inline]
+    fn get_adjacent(&self, offset: [usize; 3], face: Face) -> Option<Self::Voxel> {
+        self.adj[face].map(|c| c[Shape::linearize(offset)] >= self.threshold)
+    }
+}
+
+/// A [`MesherView`] reading voxels through closures, for storage that isn't a plain voxel array,
+/// e.g. voxels interleaved with metadata.
+///
+/// `get_adjacent` is used for [`MesherViewAdjacent`], use `|_, _| None` when there are no adjacent chunks.
+#[derive(Debug, Clone, Copy)]
+pub struct FnView<F, A> {
+    pub get: F,
+    pub get_adjacent: A,
+}
+
+impl<X, F, A> MesherView for FnView<F, A>
+where
+    F: Fn([usize; 3]) -> X,
+{
+    type Voxel = X;
+
+    #[inline]
+    fn get(&self, offset: [usize; 3]) -> Self::Voxel {
+        (self.get)(offset)
+    }
+}
+
+impl<X, F, A> MesherViewAdjacent for FnView<F, A>
+where
+    F: Fn([usize; 3]) -> X,
+    A: Fn([usize; 3], Face) -> Option<X>,
+{
+    #[inline]
+    fn get_adjacent(&self, offset: [usize; 3], face: Face) -> Option<Self::Voxel> {
+        (self.get_adjacent)(offset, face)
+    }
+}